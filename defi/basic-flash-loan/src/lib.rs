@@ -1,34 +1,89 @@
 use scrypto::prelude::*;
 
+/// The number of basis points in one, which loan fees are expressed in
+pub const BASIS_POINTS: u16 = 10_000;
+
+/// The highest fee that may be charged on a loan, 5%
+pub const MAX_FEE_BPS: u16 = 500;
+
 #[derive(NonFungibleData, ScryptoSbor)]
 pub struct LoanDue {
-    pub amount_due: Decimal,
+    pub resource_address: ResourceAddress,
+    /// The amount that was lent
+    pub principal: Decimal,
+    /// The fee owed on top of the principal, at the fee rate in force when the loan was taken
+    pub fee: Decimal,
+}
+
+/// The liquidity available for loans of a single resource, along with the pool-share tokens handed out to the
+/// lenders of that resource.
+#[derive(ScryptoSbor)]
+pub struct LoanPool {
+    loan_vault: Vault,
+    share_resource_manager: ResourceManager,
+    /// The amount of pool-share tokens in circulation.  Each of them is redeemable for an equal part of the vault.
+    total_shares: Decimal,
+    /// The number of loans of this resource which have been taken out and not yet repaid in this transaction
+    outstanding_loans: u64,
 }
 
 #[blueprint]
 mod basic_flash_loan {
+    enable_method_auth! {
+        roles {
+            admin => updatable_by: [];
+        },
+        methods {
+            available_liquidity => PUBLIC;
+            share_resource_address => PUBLIC;
+            fee_for => PUBLIC;
+            get_fee_bps => PUBLIC;
+            set_fee_bps => restrict_to: [admin];
+            add_liquidity => PUBLIC;
+            remove_liquidity => PUBLIC;
+            take_loan => PUBLIC;
+            repay_loans => PUBLIC;
+        }
+    }
     struct BasicFlashLoan {
-        loan_vault: Vault,
+        loan_pools: HashMap<ResourceAddress, LoanPool>,
         transient_resource_manager: ResourceManager,
+        /// The fee charged on every loan, in basis points of the principal
+        fee_bps: u16,
     }
 
     impl BasicFlashLoan {
         /// The most elementary possible flash loan.  Creates a loan pool from whatever is initially supplied,
-        /// provides loans with a .1% fee, and lets anyone freely add liquidity.
+        /// provides loans with a fee of `fee_bps` basis points, and lets anyone freely add liquidity of any
+        /// fungible resource.
         ///
-        /// Does NOT reward liquidity providers in any way or provide a way to remove liquidity from the pool.
-        /// Minting LP tokens for rewards, and removing liquidity, is covered in other examples, such as:
-        /// https://github.com/radixdlt/scrypto-examples/tree/main/defi/radiswap
-        pub fn instantiate_default(initial_liquidity: Bucket) -> Global<BasicFlashLoan> {
+        /// Lenders receive pool-share tokens for the resource they supplied.  The loan fees are paid into the pool,
+        /// so the shares are redeemable for more and more of the resource as loans are repaid.  The pool-share tokens
+        /// for the initial liquidity are returned along with the component and an admin badge, which may change
+        /// the fee up to `MAX_FEE_BPS`.
+        pub fn instantiate_default(
+            initial_liquidity: Bucket,
+            fee_bps: u16,
+        ) -> (Global<BasicFlashLoan>, Bucket, Bucket) {
+            Self::assert_valid_fee(fee_bps);
 
-            let (address_reservation, component_address) = 
+            let (address_reservation, component_address) =
                 Runtime::allocate_component_address(BasicFlashLoan::blueprint_id());
 
+            let admin_badge = ResourceBuilder::new_fungible(OwnerRole::None)
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata(metadata!(
+                    init {
+                        "name" => "BasicFlashLoan admin badge".to_owned(), locked;
+                    }
+                ))
+                .mint_initial_supply(1);
+
             // Define a "transient" resource which can never be deposited once created, only burned
             let transient_token_manager = ResourceBuilder::new_ruid_non_fungible::<LoanDue>(OwnerRole::None)
                 .metadata(metadata!(
                     init {
-                        "name" => 
+                        "name" =>
                         "Promise token for BasicFlashLoan - must be returned to be burned!".to_owned(), locked;
                     }
                 ))
@@ -44,72 +99,248 @@ mod basic_flash_loan {
                     depositor => rule!(deny_all);
                     depositor_updater => rule!(deny_all);
                 ))
-                    
+
                 .create_with_no_initial_supply();
 
-            Self {
-                loan_vault: Vault::with_bucket(initial_liquidity),
+            let component = Self {
+                loan_pools: HashMap::new(),
                 transient_resource_manager: transient_token_manager,
+                fee_bps,
             }
             .instantiate()
             .prepare_to_globalize(OwnerRole::None)
+            .roles(roles!(
+                admin => rule!(require(admin_badge.resource_address()));
+            ))
             .with_address(address_reservation)
-            .globalize()
+            .globalize();
+
+            // The initial liquidity is supplied like any other, so that its pool-share tokens are minted the same way
+            let initial_shares = component.add_liquidity(initial_liquidity);
+
+            (component, initial_shares, admin_badge.into())
+        }
+
+        pub fn available_liquidity(&self, resource_address: ResourceAddress) -> Decimal {
+            self.loan_pools
+                .get(&resource_address)
+                .map(|loan_pool| loan_pool.loan_vault.amount())
+                .unwrap_or(Decimal::ZERO)
+        }
+
+        /// Returns the address of the pool-share tokens given to the lenders of the given resource
+        pub fn share_resource_address(&self, resource_address: ResourceAddress) -> ResourceAddress {
+            self.loan_pools
+                .get(&resource_address)
+                .expect("No loan pool exists for this resource")
+                .share_resource_manager
+                .address()
+        }
+
+        /// Returns the fee owed on a loan of the given amount at the current fee rate.  A loan of `amount` must be
+        /// repaid with `amount` plus this fee.
+        pub fn fee_for(&self, amount: Decimal) -> Decimal {
+            amount
+                .checked_mul(self.fee_bps)
+                .unwrap()
+                .checked_div(BASIS_POINTS)
+                .unwrap()
         }
 
-        pub fn available_liquidity(&self) -> Decimal {
-            self.loan_vault.amount()
+        pub fn get_fee_bps(&self) -> u16 {
+            self.fee_bps
         }
 
-        pub fn add_liquidity(&mut self, tokens: Bucket) {
-            self.loan_vault.put(tokens);
+        /// Changes the fee charged on loans taken from now on.  Loans which are already outstanding keep the fee
+        /// written in their terms.
+        pub fn set_fee_bps(&mut self, fee_bps: u16) {
+            Self::assert_valid_fee(fee_bps);
+            self.fee_bps = fee_bps;
         }
 
-        pub fn take_loan(&mut self, loan_amount: Decimal) -> (Bucket, Bucket) {
+        /// Adds fungible tokens to the loan pool of their resource, creating the pool if this is the first time the
+        /// resource is supplied, and returns pool-share tokens in proportion to the part of the pool that was
+        /// supplied.
+        pub fn add_liquidity(&mut self, tokens: Bucket) -> Bucket {
+            let resource_address = tokens.resource_address();
             assert!(
-                loan_amount <= self.loan_vault.amount(),
-                "Not enough liquidity to supply this loan!"
+                resource_address.is_fungible(),
+                "Only fungible resources can be lent"
+            );
+
+            let loan_pool = self
+                .loan_pools
+                .entry(resource_address)
+                .or_insert_with(|| Self::new_loan_pool(resource_address));
+            assert!(
+                loan_pool.outstanding_loans == 0,
+                "Liquidity can not be added while a loan of this resource is outstanding"
+            );
+
+            // The first lender sets the value of a share to one token, later lenders get shares at the current value
+            let pool_amount = loan_pool.loan_vault.amount();
+            let shares_amount = if loan_pool.total_shares.is_zero() || pool_amount.is_zero() {
+                tokens.amount()
+            } else {
+                tokens
+                    .amount()
+                    .checked_mul(loan_pool.total_shares)
+                    .unwrap()
+                    .checked_div(pool_amount)
+                    .unwrap()
+            };
+
+            loan_pool.loan_vault.put(tokens);
+            loan_pool.total_shares = loan_pool.total_shares.checked_add(shares_amount).unwrap();
+            loan_pool.share_resource_manager.mint(shares_amount)
+        }
+
+        /// Burns the given pool-share tokens and returns their part of the loan pool, fees included
+        pub fn remove_liquidity(&mut self, shares: Bucket) -> Bucket {
+            let loan_pool = self
+                .loan_pools
+                .values_mut()
+                .find(|loan_pool| {
+                    loan_pool.share_resource_manager.address() == shares.resource_address()
+                })
+                .expect("Incorrect resource passed in for pool-share tokens");
+            assert!(
+                loan_pool.outstanding_loans == 0,
+                "Liquidity can not be removed while a loan of this resource is outstanding"
             );
 
-            // Calculate how much we must be repaid
-            let amount_due = loan_amount.checked_mul(dec!("1.001"));
+            let redeemed_amount = shares
+                .amount()
+                .checked_mul(loan_pool.loan_vault.amount())
+                .unwrap()
+                .checked_div(loan_pool.total_shares)
+                .unwrap();
+
+            loan_pool.total_shares = loan_pool.total_shares.checked_sub(shares.amount()).unwrap();
+            shares.burn();
+
+            loan_pool.loan_vault.take_advanced(
+                redeemed_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            )
+        }
+
+        pub fn take_loan(
+            &mut self,
+            resource_address: ResourceAddress,
+            loan_amount: Decimal,
+        ) -> (Bucket, Bucket) {
+            // Calculate the fee we must be repaid on top of the loan
+            let fee = self.fee_for(loan_amount);
+
+            let loan_pool = self
+                .loan_pools
+                .get_mut(&resource_address)
+                .expect("No loan pool exists for this resource");
+            assert!(
+                loan_amount <= loan_pool.loan_vault.amount(),
+                "Not enough liquidity to supply this loan!"
+            );
 
             // Mint an NFT with the loan terms.  Remember that this resource previously had rules defined which
             // forbid it from ever being deposited in any vault.  Thus, once it is present in the transaction
             // the only way for the TX to complete is to remove this "dangling" resource by burning it.
             //
             // Our component will control the only badge with the authority to burn the resource, so anyone taking
-            // a loan must call our repay_loan() method with an appropriate reimbursement, at which point we will
+            // a loan must call our repay_loans() method with an appropriate reimbursement, at which point we will
             // burn the NFT and allow the TX to complete.
-            let loan_terms = self.transient_resource_manager
-                .mint_ruid_non_fungible(
-                        LoanDue {
-                            amount_due: amount_due.unwrap(),
-                        },
-                    );
-            (self.loan_vault.take(loan_amount), loan_terms)
+            let loan_terms = self
+                .transient_resource_manager
+                .mint_ruid_non_fungible(LoanDue {
+                    resource_address,
+                    principal: loan_amount,
+                    fee,
+                });
+            loan_pool.outstanding_loans += 1;
+            (loan_pool.loan_vault.take(loan_amount), loan_terms)
         }
 
-        pub fn repay_loan(&mut self, loan_repayment: Bucket, loan_terms: Bucket) {
+        /// Settles every loan whose `LoanDue` is in `loan_terms` out of a single repayment, and returns whatever
+        /// is left of the repayment once the principal and fee of all of them have been taken out.  All the loans
+        /// must be of the resource the repayment is made in.
+        pub fn repay_loans(&mut self, mut loan_repayment: Bucket, loan_terms: Bucket) -> Bucket {
             assert!(
-                loan_terms.resource_address() 
-                == self.transient_resource_manager.address(),
+                loan_terms.resource_address() == self.transient_resource_manager.address(),
                 "Incorrect resource passed in for loan terms"
             );
+            assert!(!loan_terms.is_empty(), "No loan terms passed in");
+
+            // Add up what is due on all the loans, making sure each of them is repaid in the resource it was lent in
+            let resource_address = loan_repayment.resource_address();
+            let loans: Vec<NonFungible<LoanDue>> = loan_terms.as_non_fungible().non_fungibles();
+            let mut amount_due = Decimal::ZERO;
+            for loan in &loans {
+                let terms = loan.data();
+                assert!(
+                    terms.resource_address == resource_address,
+                    "Incorrect resource passed in for loan repayment"
+                );
+                amount_due = amount_due
+                    .checked_add(terms.principal)
+                    .unwrap()
+                    .checked_add(terms.fee)
+                    .unwrap();
+            }
 
             // Verify we are being sent at least the amount due
-            let terms: LoanDue = loan_terms.as_non_fungible().non_fungible().data();
             assert!(
-                loan_repayment.amount() >= terms.amount_due,
-                "Insufficient repayment given for your loan!"
+                loan_repayment.amount() >= amount_due,
+                "Insufficient repayment given for your loans!"
             );
 
-            // We could also verify that the resource being repaid is of the correct kind, and give a friendly
-            // error message if not. For this example we'll just let the engine handle that when we try to deposit
-            self.loan_vault.put(loan_repayment);
+            // The repayment, fees included, goes back into the pool and so accrues to the pool-share tokens.  Anything
+            // paid on top of that is returned to the borrower as change.  The amount due is rounded up to the
+            // divisibility of the resource, in favor of the lenders.
+            let loan_pool = self.loan_pools.get_mut(&resource_address).unwrap();
+            loan_pool.loan_vault.put(loan_repayment.take_advanced(
+                amount_due,
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            ));
+            loan_pool.outstanding_loans -= loans.len() as u64;
 
-            // We have our payment; we can now burn the transient token
+            // We have our payment; we can now burn the transient tokens
             loan_terms.burn();
+
+            loan_repayment
+        }
+
+        fn assert_valid_fee(fee_bps: u16) {
+            assert!(
+                fee_bps <= MAX_FEE_BPS,
+                "Fee must not exceed {} basis points",
+                MAX_FEE_BPS
+            );
+        }
+
+        /// Creates an empty loan pool for the given resource, along with the pool-share tokens of its lenders
+        fn new_loan_pool(resource_address: ResourceAddress) -> LoanPool {
+            let share_resource_manager = ResourceBuilder::new_fungible(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Pool share for BasicFlashLoan".to_owned(), locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => rule!(require(global_caller(Runtime::global_address())));
+                    minter_updater => rule!(deny_all);
+                ))
+                .burn_roles(burn_roles!(
+                    burner => rule!(require(global_caller(Runtime::global_address())));
+                    burner_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply();
+
+            LoanPool {
+                loan_vault: Vault::new(resource_address),
+                share_resource_manager,
+                total_shares: Decimal::ZERO,
+                outstanding_loans: 0,
+            }
         }
     }
 }
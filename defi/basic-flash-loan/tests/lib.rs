@@ -0,0 +1,449 @@
+use radix_engine::errors::{RuntimeError, SystemModuleError};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+use transaction::prelude::TransactionManifestV1;
+
+#[test]
+fn lenders_of_each_resource_get_their_own_shares() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (flash_loan, _loan_terms, _admin_badge) = instantiate_flash_loan(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        10,
+    );
+
+    // Test the `add_liquidity` method with a second resource, which gets a loan pool of its own.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_b, dec!(50))
+        .take_all_from_worktop(resource_b, "liquidity")
+        .call_method_with_name_lookup(flash_loan, "add_liquidity", |lookup| {
+            (lookup.bucket("liquidity"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `share_resource_address` and `available_liquidity` methods.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "share_resource_address",
+            manifest_args!(resource_a),
+        )
+        .call_method(
+            flash_loan,
+            "share_resource_address",
+            manifest_args!(resource_b),
+        )
+        .call_method(
+            flash_loan,
+            "available_liquidity",
+            manifest_args!(resource_a),
+        )
+        .call_method(
+            flash_loan,
+            "available_liquidity",
+            manifest_args!(resource_b),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let commit_success = receipt.expect_commit_success();
+    let shares_a: ResourceAddress = commit_success.output(0);
+    let shares_b: ResourceAddress = commit_success.output(1);
+    assert_ne!(shares_a, shares_b);
+    assert_eq!(commit_success.output::<Decimal>(2), dec!(100));
+    assert_eq!(commit_success.output::<Decimal>(3), dec!(50));
+    assert_eq!(
+        test_runner.account_balance(account_component, shares_a),
+        Some(dec!(100))
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, shares_b),
+        Some(dec!(50))
+    );
+
+    // Test the `remove_liquidity` method, the shares of B only redeem B.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, shares_b, dec!(20))
+        .take_all_from_worktop(shares_b, "shares")
+        .call_method_with_name_lookup(flash_loan, "remove_liquidity", |lookup| {
+            (lookup.bucket("shares"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(900))
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        Some(dec!(970))
+    );
+
+    // Non-fungible resources can not be lent.
+    let nft = test_runner.create_non_fungible_resource(account_component);
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, nft, dec!(1))
+        .take_all_from_worktop(nft, "liquidity")
+        .call_method_with_name_lookup(flash_loan, "add_liquidity", |lookup| {
+            (lookup.bucket("liquidity"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn admin_changes_the_fee_of_new_loans_within_the_bound() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the admin and the account of someone else
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (other_public_key, _other_private_key, _other_account_component) =
+        test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (flash_loan, loan_terms, admin_badge) = instantiate_flash_loan(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        10,
+    );
+
+    // Test the `set_fee_bps` method without the admin badge.
+    let manifest = ManifestBuilder::new()
+        .call_method(flash_loan, "set_fee_bps", manifest_args!(100u16))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&other_public_key)],
+    );
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+        )
+    });
+
+    // Test the `set_fee_bps` method with the admin badge, above and then within the 5% bound.
+    for (fee_bps, succeeds) in [(501u16, false), (100u16, true)] {
+        let manifest = ManifestBuilder::new()
+            .create_proof_from_account_of_amount(account_component, admin_badge, dec!(1))
+            .call_method(flash_loan, "set_fee_bps", manifest_args!(fee_bps))
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+
+    // Test the `fee_for` and `get_fee_bps` methods.
+    let manifest = ManifestBuilder::new()
+        .call_method(flash_loan, "fee_for", manifest_args!(dec!(50)))
+        .call_method(flash_loan, "get_fee_bps", manifest_args!())
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let commit_success = receipt.expect_commit_success();
+    assert_eq!(commit_success.output::<Decimal>(0), dec!("0.5"));
+    assert_eq!(commit_success.output::<u16>(1), 100);
+
+    // A loan of 50 A now costs 0.5 A, the old fee of 0.05 A is not enough.
+    for (fee, succeeds) in [(dec!("0.05"), false), (dec!("0.5"), true)] {
+        let manifest = take_and_repay_loan(
+            flash_loan,
+            loan_terms,
+            account_component,
+            resource_a,
+            dec!(50),
+            fee,
+        );
+        let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!("899.5"))
+    );
+}
+
+#[test]
+fn fee_above_the_bound_is_rejected_at_instantiation() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `instantiate_default` function with a fee of 5.01%.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(100))
+        .take_all_from_worktop(resource_a, "liquidity")
+        .call_function_with_name_lookup(
+            package_address,
+            "BasicFlashLoan",
+            "instantiate_default",
+            |lookup| (lookup.bucket("liquidity"), 501u16),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn loans_taken_together_are_repaid_together_and_the_fees_go_to_the_lenders() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which lends and borrows at the same time
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (flash_loan, loan_terms, _admin_badge) = instantiate_flash_loan(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        10,
+    );
+
+    // Test the `take_loan` method twice and the `repay_loans` method once for both loans. The
+    // 50.05 A due are taken out of the 60 A repayment and the other 9.95 A are returned.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "take_loan",
+            manifest_args!(resource_a, dec!(30)),
+        )
+        .call_method(
+            flash_loan,
+            "take_loan",
+            manifest_args!(resource_a, dec!(20)),
+        )
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "repayment")
+        .take_all_from_worktop(loan_terms, "loan_terms")
+        .call_method_with_name_lookup(flash_loan, "repay_loans", |lookup| {
+            (lookup.bucket("repayment"), lookup.bucket("loan_terms"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!("899.95"))
+    );
+
+    // Test the `remove_liquidity` method, the shares are now worth the liquidity plus the fees.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "share_resource_address",
+            manifest_args!(resource_a),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let shares_a: ResourceAddress = receipt.expect_commit_success().output(0);
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, shares_a, dec!(100))
+        .take_all_from_worktop(shares_a, "shares")
+        .call_method_with_name_lookup(flash_loan, "remove_liquidity", |lookup| {
+            (lookup.bucket("shares"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(1000))
+    );
+}
+
+#[test]
+fn repayment_in_another_resource_is_rejected() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (flash_loan, loan_terms, _admin_badge) = instantiate_flash_loan(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        10,
+    );
+
+    // Test the `repay_loans` method with B for a loan of A.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "take_loan",
+            manifest_args!(resource_a, dec!(50)),
+        )
+        .withdraw_from_account(account_component, resource_b, dec!(60))
+        .take_all_from_worktop(resource_b, "repayment")
+        .take_all_from_worktop(loan_terms, "loan_terms")
+        .call_method_with_name_lookup(flash_loan, "repay_loans", |lookup| {
+            (lookup.bucket("repayment"), lookup.bucket("loan_terms"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn repayment_is_rounded_up_to_the_divisibility_of_the_resource() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // A resource which can only be split into hundredths.
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 2u8, account_component);
+    let (flash_loan, loan_terms, _admin_badge) = instantiate_flash_loan(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        10,
+    );
+
+    // A loan of 1.23 A owes 1.23123 A, which is rounded up to 1.24 A out of the 2.23 A repayment.
+    let manifest = take_and_repay_loan(
+        flash_loan,
+        loan_terms,
+        account_component,
+        resource_a,
+        dec!("1.23"),
+        dec!(1),
+    );
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!("899.99"))
+    );
+
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "available_liquidity",
+            manifest_args!(resource_a),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    assert_eq!(
+        receipt.expect_commit_success().output::<Decimal>(0),
+        dec!("100.01")
+    );
+}
+
+/// Instantiates a flash loan charging the given fee, with 100 of the given resource as its initial
+/// liquidity. Returns the flash loan, the resource of its loan terms and its admin badge.
+fn instantiate_flash_loan(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    resource_address: ResourceAddress,
+    fee_bps: u16,
+) -> (ComponentAddress, ResourceAddress, ResourceAddress) {
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Test the `instantiate_default` function.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_address, dec!(100))
+        .take_all_from_worktop(resource_address, "liquidity")
+        .call_function_with_name_lookup(
+            package_address,
+            "BasicFlashLoan",
+            "instantiate_default",
+            |lookup| (lookup.bucket("liquidity"), fee_bps),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let commit_success = receipt.expect_commit_success();
+
+    // The admin badge is created first and the resource of the loan terms second.
+    (
+        commit_success.new_component_addresses()[0],
+        commit_success.new_resource_addresses()[1],
+        commit_success.new_resource_addresses()[0],
+    )
+}
+
+/// Builds a manifest which takes a loan of `loan_amount` and repays it out of the loan plus `fee`
+/// withdrawn from the account, depositing whatever is returned in the account.
+fn take_and_repay_loan(
+    flash_loan: ComponentAddress,
+    loan_terms: ResourceAddress,
+    account_component: ComponentAddress,
+    resource_address: ResourceAddress,
+    loan_amount: Decimal,
+    fee: Decimal,
+) -> TransactionManifestV1 {
+    ManifestBuilder::new()
+        .call_method(
+            flash_loan,
+            "take_loan",
+            manifest_args!(resource_address, loan_amount),
+        )
+        .withdraw_from_account(account_component, resource_address, fee)
+        .take_all_from_worktop(resource_address, "repayment")
+        .take_all_from_worktop(loan_terms, "loan_terms")
+        .call_method_with_name_lookup(flash_loan, "repay_loans", |lookup| {
+            (lookup.bucket("repayment"), lookup.bucket("loan_terms"))
+        })
+        .deposit_batch(account_component)
+        .build()
+}
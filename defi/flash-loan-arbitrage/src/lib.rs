@@ -0,0 +1,64 @@
+use scrypto::prelude::*;
+
+#[blueprint]
+mod arbitrageur {
+    /// An Arbitrageur closes the price gap between two Radiswap pools of the same pair without
+    /// holding any of the pair itself. It borrows from a BasicFlashLoan, sells the loan in the pool
+    /// where it is worth more, buys it back in the pool where it is worth less, repays the loan and
+    /// hands the difference to the caller, all in a single transaction.
+    ///
+    /// The flash loan and the pools are called as `Global<AnyComponent>` rather than through
+    /// `extern_blueprint!`, because their packages are published alongside this one and so their
+    /// addresses are not known in advance.
+    struct Arbitrageur {
+        /// The BasicFlashLoan component that arbitrages are funded from.
+        flash_loan: Global<AnyComponent>,
+    }
+
+    impl Arbitrageur {
+        pub fn instantiate_arbitrageur(flash_loan: Global<AnyComponent>) -> Global<Arbitrageur> {
+            Self { flash_loan }
+                .instantiate()
+                .prepare_to_globalize(OwnerRole::None)
+                .globalize()
+        }
+
+        /// Borrows `loan_amount` of the given resource, swaps it through `first_pool` and swaps the
+        /// proceeds back through `second_pool`, then repays the loan and returns what is left over.
+        /// The transaction fails unless at least `min_profit` is left over, which also covers the
+        /// case where the pools are not mispriced at all and the swaps do not even make up for the
+        /// loan fee.
+        pub fn arbitrage(
+            &mut self,
+            resource_address: ResourceAddress,
+            loan_amount: Decimal,
+            first_pool: Global<AnyComponent>,
+            second_pool: Global<AnyComponent>,
+            min_profit: Decimal,
+        ) -> Bucket {
+            let (loan, loan_terms): (Bucket, Bucket) = self
+                .flash_loan
+                .call("take_loan", &(resource_address, loan_amount));
+
+            let other_resource: Bucket = first_pool.call("swap", &(loan,));
+            let proceeds: Bucket = second_pool.call("swap", &(other_resource,));
+            assert_eq!(
+                proceeds.resource_address(),
+                resource_address,
+                "Both pools must trade the borrowed resource against the same other resource"
+            );
+
+            // Whatever is left of the proceeds once the principal and fee of the loan are paid is
+            // our profit.
+            let profit: Bucket = self.flash_loan.call("repay_loans", &(proceeds, loan_terms));
+            assert!(
+                profit.amount() >= min_profit,
+                "Profit of {} is below the minimum profit of {}",
+                profit.amount(),
+                min_profit
+            );
+
+            profit
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn arbitrage_between_mispriced_pools_keeps_the_profit() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(10000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(10000), 18u8, account_component);
+    let (arbitrageur, cheap_pool, rich_pool) = instantiate_arbitrage(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `arbitrage` method, selling the borrowed A where it is worth 2 B and buying it back
+    // where it is worth 1 B.
+    let balance_a = test_runner
+        .account_balance(account_component, resource_a)
+        .unwrap();
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            arbitrageur,
+            "arbitrage",
+            manifest_args!(resource_a, dec!(10), rich_pool, cheap_pool, dec!(1)),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    // 10 A fetch about 18.13 B, which buy back about 15.31 A, of which 10.01 A repay the loan.
+    let profit = test_runner
+        .account_balance(account_component, resource_a)
+        .unwrap()
+        .checked_sub(balance_a)
+        .unwrap();
+    assert!(profit > dec!("5.2") && profit < dec!("5.4"));
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        Some(dec!(9700))
+    );
+}
+
+#[test]
+fn arbitrage_without_profit_fails() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(10000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(10000), 18u8, account_component);
+    let (arbitrageur, cheap_pool, rich_pool) = instantiate_arbitrage(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        resource_b,
+    );
+
+    // Going through the pools the wrong way round loses A, so the loan can not be repaid.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            arbitrageur,
+            "arbitrage",
+            manifest_args!(resource_a, dec!(10), cheap_pool, rich_pool, dec!(0)),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+}
+
+/// Publishes the BasicFlashLoan and Radiswap packages next to this one, and instantiates a flash
+/// loan lending 1000 A, a pool where A is worth 1 B, a pool where A is worth 2 B and an Arbitrageur
+/// funded by the flash loan. Returns the Arbitrageur, the pool where A is cheap and the pool where
+/// A is expensive.
+fn instantiate_arbitrage(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    resource_a: ResourceAddress,
+    resource_b: ResourceAddress,
+) -> (ComponentAddress, ComponentAddress, ComponentAddress) {
+    // Publish packages
+    let package_address = test_runner.compile_and_publish(this_package!());
+    let flash_loan_package = test_runner.compile_and_publish("../basic-flash-loan");
+    let radiswap_package = test_runner.compile_and_publish("../radiswap");
+
+    // Instantiate a flash loan charging a 0.1% fee.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(1000))
+        .take_all_from_worktop(resource_a, "liquidity")
+        .call_function_with_name_lookup(
+            flash_loan_package,
+            "BasicFlashLoan",
+            "instantiate_default",
+            |lookup| (lookup.bucket("liquidity"), 10u16),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let flash_loan = receipt.expect_commit_success().new_component_addresses()[0];
+
+    let cheap_pool = instantiate_radiswap_with_liquidity(
+        test_runner,
+        public_key,
+        account_component,
+        radiswap_package,
+        (resource_a, dec!(100)),
+        (resource_b, dec!(100)),
+    );
+    let rich_pool = instantiate_radiswap_with_liquidity(
+        test_runner,
+        public_key,
+        account_component,
+        radiswap_package,
+        (resource_a, dec!(100)),
+        (resource_b, dec!(200)),
+    );
+
+    // Test the `instantiate_arbitrageur` function.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "Arbitrageur",
+            "instantiate_arbitrageur",
+            manifest_args!(flash_loan),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let arbitrageur = receipt.expect_commit_success().new_component_addresses()[0];
+
+    (arbitrageur, cheap_pool, rich_pool)
+}
+
+/// Instantiates a Radiswap pool with a 0.3% fee and funds it with the given amounts of both
+/// resources.
+fn instantiate_radiswap_with_liquidity(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    radiswap_package: PackageAddress,
+    (resource_address1, amount1): (ResourceAddress, Decimal),
+    (resource_address2, amount2): (ResourceAddress, Decimal),
+) -> ComponentAddress {
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            radiswap_package,
+            "Radiswap",
+            "new",
+            manifest_args!(
+                OwnerRole::None,
+                rule!(deny_all),
+                resource_address1,
+                resource_address2,
+                30u16
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let radiswap = receipt.expect_commit_success().new_component_addresses()[0];
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_address1, amount1)
+        .withdraw_from_account(account_component, resource_address2, amount2)
+        .take_all_from_worktop(resource_address1, "resource1")
+        .take_all_from_worktop(resource_address2, "resource2")
+        .call_method_with_name_lookup(radiswap, "add_liquidity", |lookup| {
+            (lookup.bucket("resource1"), lookup.bucket("resource2"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    receipt.expect_commit_success();
+
+    radiswap
+}
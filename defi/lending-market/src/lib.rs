@@ -0,0 +1,654 @@
+use scrypto::prelude::*;
+
+/// The share of the value of the debt repaid by a liquidator that they receive on top in
+/// collateral, in basis points.
+pub const LIQUIDATION_BONUS_BPS: u16 = 500;
+
+pub const BASIS_POINTS: u16 = 10_000;
+
+/// The collateral posted and the debt owed by a borrower, held by the borrower as an NFT. Debts are
+/// recorded as debt shares of their market, which grow in value as interest accrues.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct BorrowPosition {
+    #[mutable]
+    pub collateral: HashMap<ResourceAddress, Decimal>,
+    #[mutable]
+    pub debt_shares: HashMap<ResourceAddress, Decimal>,
+}
+
+/// Everything the lending market keeps track of for a single resource.
+#[derive(ScryptoSbor)]
+pub struct Market {
+    /// The deposits of this resource which are not lent out.
+    liquidity: Vault,
+    /// The collateral of this resource posted by all borrowers.
+    collateral: Vault,
+    /// The resource manager of the receipt tokens given out to depositors of this resource.
+    receipt_manager: ResourceManager,
+    /// The amount of receipt tokens in circulation.
+    total_receipts: Decimal,
+    /// The sum of the debt shares of all borrowers of this resource.
+    total_debt_shares: Decimal,
+    /// The amount owed per debt share, which starts at one and grows as interest accrues.
+    borrow_index: Decimal,
+    /// The epoch up to which interest has been accrued.
+    last_update_epoch: Epoch,
+    /// The price of the resource in the unit of account of the lending market.
+    price: Decimal,
+    /// The share of the value of the collateral of this resource which may be borrowed against.
+    loan_to_value: Decimal,
+    /// The share of the value of the collateral of this resource which the debt of a position may
+    /// reach before the position can be liquidated.
+    liquidation_threshold: Decimal,
+    /// The interest rate per epoch when nothing is borrowed.
+    base_rate: Decimal,
+    /// The interest rate per epoch added on top of the base rate when everything is borrowed.
+    rate_slope: Decimal,
+}
+
+/// Emitted when a borrow position is opened, which is how liquidators find the positions that they
+/// may liquidate.
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct PositionOpenedEvent {
+    pub position_id: NonFungibleLocalId,
+}
+
+#[blueprint]
+#[events(PositionOpenedEvent)]
+mod lending_market {
+    enable_method_auth! {
+        methods {
+            add_market => restrict_to: [OWNER];
+            set_price => restrict_to: [OWNER];
+            deposit => PUBLIC;
+            withdraw => PUBLIC;
+            open_position => PUBLIC;
+            add_collateral => PUBLIC;
+            remove_collateral => PUBLIC;
+            borrow => PUBLIC;
+            repay => PUBLIC;
+            liquidate => PUBLIC;
+            get_receipt_resource_address => PUBLIC;
+            get_debt => PUBLIC;
+            get_utilization => PUBLIC;
+            get_borrow_rate => PUBLIC;
+        }
+    }
+
+    /// A LendingMarket pools the deposits of several fungible resources and lends them out for as
+    /// long as the borrower likes, against collateral in any of the other resources.
+    ///
+    /// Depositors receive receipt tokens for the resource they deposited. The interest paid by the
+    /// borrowers of a resource stays in its market, so the receipt tokens are redeemable for more
+    /// and more of the resource. The interest rate of each market rises with its utilization, the
+    /// share of its deposits which is lent out, which draws in deposits when they run short.
+    ///
+    /// Borrowers may borrow up to the loan-to-value of their collateral. Once their debt grows past
+    /// the liquidation threshold of their collateral, anyone may repay part of it and seize the
+    /// same value of collateral plus a bonus. The prices used to value debts and collateral are
+    /// set by the owner, standing in for an oracle.
+    struct LendingMarket {
+        markets: HashMap<ResourceAddress, Market>,
+        /// The resource manager of the borrow position NFTs given out to borrowers.
+        position_manager: ResourceManager,
+    }
+
+    impl LendingMarket {
+        pub fn instantiate_lending_market(owner_role: OwnerRole) -> Global<LendingMarket> {
+            let (address_reservation, component_address) =
+                Runtime::allocate_component_address(LendingMarket::blueprint_id());
+
+            let position_manager = ResourceBuilder::new_ruid_non_fungible::<BorrowPosition>(
+                OwnerRole::None,
+            )
+            .metadata(metadata!(
+                init {
+                    "name" => "Lending Market Borrow Position".to_owned(), locked;
+                }
+            ))
+            .mint_roles(mint_roles!(
+                minter => rule!(require(global_caller(component_address)));
+                minter_updater => rule!(deny_all);
+            ))
+            .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                non_fungible_data_updater => rule!(require(global_caller(component_address)));
+                non_fungible_data_updater_updater => rule!(deny_all);
+            ))
+            .create_with_no_initial_supply();
+
+            Self {
+                markets: HashMap::new(),
+                position_manager,
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role)
+            .with_address(address_reservation)
+            .globalize()
+        }
+
+        /// Opens a market for the given resource, which may then be deposited, borrowed and posted
+        /// as collateral. The interest rate per epoch goes from `base_rate` when nothing is
+        /// borrowed up to `base_rate + rate_slope` when everything is.
+        pub fn add_market(
+            &mut self,
+            resource_address: ResourceAddress,
+            price: Decimal,
+            loan_to_value: Decimal,
+            liquidation_threshold: Decimal,
+            base_rate: Decimal,
+            rate_slope: Decimal,
+        ) {
+            assert!(
+                !self.markets.contains_key(&resource_address),
+                "A market already exists for this resource"
+            );
+            assert!(price > Decimal::ZERO, "Price must be positive");
+            assert!(
+                loan_to_value >= Decimal::ZERO
+                    && loan_to_value <= liquidation_threshold
+                    && liquidation_threshold < Decimal::ONE,
+                "Loan-to-value must not exceed the liquidation threshold, which must be below one"
+            );
+            assert!(
+                base_rate >= Decimal::ZERO && rate_slope >= Decimal::ZERO,
+                "Interest rates must not be negative"
+            );
+
+            let receipt_manager = ResourceBuilder::new_fungible(OwnerRole::None)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Lending Market Receipt".to_owned(), locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => rule!(require(global_caller(Runtime::global_address())));
+                    minter_updater => rule!(deny_all);
+                ))
+                .burn_roles(burn_roles!(
+                    burner => rule!(require(global_caller(Runtime::global_address())));
+                    burner_updater => rule!(deny_all);
+                ))
+                .create_with_no_initial_supply();
+
+            self.markets.insert(
+                resource_address,
+                Market {
+                    liquidity: Vault::new(resource_address),
+                    collateral: Vault::new(resource_address),
+                    receipt_manager,
+                    total_receipts: Decimal::ZERO,
+                    total_debt_shares: Decimal::ZERO,
+                    borrow_index: Decimal::ONE,
+                    last_update_epoch: Runtime::current_epoch(),
+                    price,
+                    loan_to_value,
+                    liquidation_threshold,
+                    base_rate,
+                    rate_slope,
+                },
+            );
+        }
+
+        /// Updates the price of a resource in the unit of account of the lending market.
+        pub fn set_price(&mut self, resource_address: ResourceAddress, price: Decimal) {
+            assert!(price > Decimal::ZERO, "Price must be positive");
+            self.market_mut(resource_address).price = price;
+        }
+
+        /// Deposits the given tokens in their market and returns receipt tokens in proportion to
+        /// the part of the market, interest included, that was deposited.
+        pub fn deposit(&mut self, tokens: Bucket) -> Bucket {
+            self.accrue_interest();
+
+            let market = self.market_mut(tokens.resource_address());
+            let total_supplied = Self::total_supplied(market);
+            let receipts_amount = if market.total_receipts.is_zero() || total_supplied.is_zero() {
+                tokens.amount()
+            } else {
+                tokens
+                    .amount()
+                    .checked_mul(market.total_receipts)
+                    .unwrap()
+                    .checked_div(total_supplied)
+                    .unwrap()
+            };
+
+            market.liquidity.put(tokens);
+            market.total_receipts = market.total_receipts.checked_add(receipts_amount).unwrap();
+            market.receipt_manager.mint(receipts_amount)
+        }
+
+        /// Burns the given receipt tokens and returns their part of the market, interest included.
+        /// Only the deposits which are not lent out can be withdrawn.
+        pub fn withdraw(&mut self, receipts: Bucket) -> Bucket {
+            self.accrue_interest();
+
+            let market = self
+                .markets
+                .values_mut()
+                .find(|market| market.receipt_manager.address() == receipts.resource_address())
+                .expect("Incorrect resource passed in for receipt tokens");
+            let redeemed_amount = receipts
+                .amount()
+                .checked_mul(Self::total_supplied(market))
+                .unwrap()
+                .checked_div(market.total_receipts)
+                .unwrap();
+            assert!(
+                redeemed_amount <= market.liquidity.amount(),
+                "Not enough liquidity to withdraw {}",
+                redeemed_amount
+            );
+
+            market.total_receipts = market
+                .total_receipts
+                .checked_sub(receipts.amount())
+                .unwrap();
+            receipts.burn();
+
+            market.liquidity.take_advanced(
+                redeemed_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            )
+        }
+
+        /// Posts the given collateral in a new borrow position and returns the position NFT, which
+        /// is needed to borrow against it.
+        pub fn open_position(&mut self, collateral: Bucket) -> Bucket {
+            let resource_address = collateral.resource_address();
+            let amount = collateral.amount();
+            self.market_mut(resource_address).collateral.put(collateral);
+
+            let mut position_collateral = HashMap::new();
+            position_collateral.insert(resource_address, amount);
+            let position = self
+                .position_manager
+                .mint_ruid_non_fungible(BorrowPosition {
+                    collateral: position_collateral,
+                    debt_shares: HashMap::new(),
+                });
+            Runtime::emit_event(PositionOpenedEvent {
+                position_id: position.as_non_fungible().non_fungible_local_id(),
+            });
+
+            position
+        }
+
+        /// Posts more collateral in the presented position.
+        pub fn add_collateral(&mut self, position: Proof, collateral: Bucket) {
+            let (position_id, mut position) = self.check_position(position);
+
+            let resource_address = collateral.resource_address();
+            let amount = collateral.amount();
+            self.market_mut(resource_address).collateral.put(collateral);
+
+            let posted = position
+                .collateral
+                .entry(resource_address)
+                .or_insert(Decimal::ZERO);
+            *posted = posted.checked_add(amount).unwrap();
+            self.save_position(&position_id, position);
+        }
+
+        /// Takes collateral out of the presented position, as long as what is left is still worth
+        /// enough to cover its debt at the loan-to-value of the collateral.
+        pub fn remove_collateral(
+            &mut self,
+            position: Proof,
+            resource_address: ResourceAddress,
+            amount: Decimal,
+        ) -> Bucket {
+            self.accrue_interest();
+            let (position_id, mut position) = self.check_position(position);
+
+            let posted = position
+                .collateral
+                .get_mut(&resource_address)
+                .expect("No collateral of this resource is posted in the position");
+            assert!(
+                amount <= *posted,
+                "Only {} of this resource is posted in the position",
+                posted
+            );
+            *posted = posted.checked_sub(amount).unwrap();
+            if posted.is_zero() {
+                position.collateral.remove(&resource_address);
+            }
+            self.assert_within_loan_to_value(&position);
+            self.save_position(&position_id, position);
+
+            self.market_mut(resource_address).collateral.take(amount)
+        }
+
+        /// Lends the given amount to the presented position, as long as its collateral is worth
+        /// enough to cover all of its debt at the loan-to-value of the collateral.
+        pub fn borrow(
+            &mut self,
+            position: Proof,
+            resource_address: ResourceAddress,
+            amount: Decimal,
+        ) -> Bucket {
+            self.accrue_interest();
+            let (position_id, mut position) = self.check_position(position);
+
+            let market = self.market_mut(resource_address);
+            assert!(
+                amount <= market.liquidity.amount(),
+                "Not enough liquidity to lend {}",
+                amount
+            );
+            // The debt shares are rounded up, so that the debt is never less than the loan.
+            let mut debt_shares = amount.checked_div(market.borrow_index).unwrap();
+            if debt_shares.checked_mul(market.borrow_index).unwrap() < amount {
+                debt_shares = debt_shares
+                    .checked_add(dec!("0.000000000000000001"))
+                    .unwrap();
+            }
+            market.total_debt_shares = market.total_debt_shares.checked_add(debt_shares).unwrap();
+            let loan = market.liquidity.take(amount);
+
+            let owed_shares = position
+                .debt_shares
+                .entry(resource_address)
+                .or_insert(Decimal::ZERO);
+            *owed_shares = owed_shares.checked_add(debt_shares).unwrap();
+            self.assert_within_loan_to_value(&position);
+            self.save_position(&position_id, position);
+
+            loan
+        }
+
+        /// Repays debt of the presented position in the resource of the repayment, and returns
+        /// whatever is paid on top of the debt as change.
+        pub fn repay(&mut self, position: Proof, repayment: Bucket) -> Bucket {
+            self.accrue_interest();
+            let (position_id, mut position) = self.check_position(position);
+
+            let change = self.take_repayment(&mut position, repayment);
+            self.save_position(&position_id, position);
+
+            change
+        }
+
+        /// Repays debt of a position whose debt has grown past the liquidation threshold of its
+        /// collateral, in the resource of the repayment. In return the liquidator gets collateral
+        /// of the given resource worth the repaid debt plus the liquidation bonus, or all of the
+        /// collateral of that resource if there is less. Returns the seized collateral along with
+        /// whatever is paid on top of the debt as change.
+        pub fn liquidate(
+            &mut self,
+            position_id: NonFungibleLocalId,
+            repayment: Bucket,
+            collateral_resource_address: ResourceAddress,
+        ) -> (Bucket, Bucket) {
+            self.accrue_interest();
+            let mut position: BorrowPosition =
+                self.position_manager.get_non_fungible_data(&position_id);
+            let (_, liquidation_limit, debt_value) = self.valuate(&position);
+            assert!(
+                debt_value > liquidation_limit,
+                "Position is not undercollateralized"
+            );
+
+            let debt_price = self.market(repayment.resource_address()).price;
+            let repayment_amount = repayment.amount();
+            let change = self.take_repayment(&mut position, repayment);
+            let repaid_amount = repayment_amount.checked_sub(change.amount()).unwrap();
+
+            // The liquidator is paid in collateral for the value they repaid, plus the bonus
+            let seized_value = repaid_amount
+                .checked_mul(debt_price)
+                .unwrap()
+                .checked_mul(BASIS_POINTS + LIQUIDATION_BONUS_BPS)
+                .unwrap()
+                .checked_div(BASIS_POINTS)
+                .unwrap();
+            let posted = position
+                .collateral
+                .get_mut(&collateral_resource_address)
+                .expect("No collateral of this resource is posted in the position");
+            let seized_amount = cmp::min(
+                seized_value
+                    .checked_div(self.market(collateral_resource_address).price)
+                    .unwrap(),
+                *posted,
+            );
+            // The vault rounds the seized amount down to the divisibility of the collateral, and
+            // the position only gives up what was actually taken.
+            let seized_collateral = self
+                .market_mut(collateral_resource_address)
+                .collateral
+                .take_advanced(
+                    seized_amount,
+                    WithdrawStrategy::Rounded(RoundingMode::ToZero),
+                );
+            *posted = posted.checked_sub(seized_collateral.amount()).unwrap();
+            if posted.is_zero() {
+                position.collateral.remove(&collateral_resource_address);
+            }
+            self.save_position(&position_id, position);
+
+            (seized_collateral, change)
+        }
+
+        pub fn get_receipt_resource_address(
+            &self,
+            resource_address: ResourceAddress,
+        ) -> ResourceAddress {
+            self.market(resource_address).receipt_manager.address()
+        }
+
+        /// Returns the debt of the given position in the given resource, interest included.
+        pub fn get_debt(
+            &self,
+            position_id: NonFungibleLocalId,
+            resource_address: ResourceAddress,
+        ) -> Decimal {
+            let position: BorrowPosition =
+                self.position_manager.get_non_fungible_data(&position_id);
+            let debt_shares = position
+                .debt_shares
+                .get(&resource_address)
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+
+            debt_shares
+                .checked_mul(Self::calculate_borrow_index(self.market(resource_address)))
+                .unwrap()
+        }
+
+        /// Returns the share of the deposits of the given resource which is lent out.
+        pub fn get_utilization(&self, resource_address: ResourceAddress) -> Decimal {
+            Self::utilization(self.market(resource_address))
+        }
+
+        /// Returns the interest rate per epoch currently paid by borrowers of the given resource.
+        pub fn get_borrow_rate(&self, resource_address: ResourceAddress) -> Decimal {
+            Self::borrow_rate(self.market(resource_address))
+        }
+
+        /// Brings the borrow index of every market up to date with the current epoch.
+        fn accrue_interest(&mut self) {
+            for market in self.markets.values_mut() {
+                market.borrow_index = Self::calculate_borrow_index(market);
+                market.last_update_epoch = Runtime::current_epoch();
+            }
+        }
+
+        /// Returns what the borrow index of the market would be if interest were accrued up to the
+        /// current epoch, at the rate given by the current utilization.
+        fn calculate_borrow_index(market: &Market) -> Decimal {
+            let elapsed_epochs =
+                Runtime::current_epoch().number() - market.last_update_epoch.number();
+            let growth = Self::borrow_rate(market)
+                .checked_mul(elapsed_epochs)
+                .unwrap();
+
+            market
+                .borrow_index
+                .checked_mul(Decimal::ONE.checked_add(growth).unwrap())
+                .unwrap()
+        }
+
+        fn borrow_rate(market: &Market) -> Decimal {
+            market
+                .base_rate
+                .checked_add(
+                    market
+                        .rate_slope
+                        .checked_mul(Self::utilization(market))
+                        .unwrap(),
+                )
+                .unwrap()
+        }
+
+        fn utilization(market: &Market) -> Decimal {
+            let total_supplied = Self::total_supplied(market);
+            if total_supplied.is_zero() {
+                return Decimal::ZERO;
+            }
+
+            Self::total_debt(market)
+                .checked_div(total_supplied)
+                .unwrap()
+        }
+
+        fn total_debt(market: &Market) -> Decimal {
+            market
+                .total_debt_shares
+                .checked_mul(market.borrow_index)
+                .unwrap()
+        }
+
+        /// The deposits of the market, including those lent out and the interest owed on them.
+        fn total_supplied(market: &Market) -> Decimal {
+            market
+                .liquidity
+                .amount()
+                .checked_add(Self::total_debt(market))
+                .unwrap()
+        }
+
+        /// Returns the borrowing power of the collateral of the position, the debt it may reach
+        /// before it can be liquidated and its current debt, all in the unit of account. Interest
+        /// must have been accrued up to the current epoch beforehand.
+        fn valuate(&self, position: &BorrowPosition) -> (Decimal, Decimal, Decimal) {
+            let mut borrowing_power = Decimal::ZERO;
+            let mut liquidation_limit = Decimal::ZERO;
+            for (resource_address, amount) in position.collateral.iter() {
+                let market = self.market(*resource_address);
+                let value = amount.checked_mul(market.price).unwrap();
+                borrowing_power = borrowing_power
+                    .checked_add(value.checked_mul(market.loan_to_value).unwrap())
+                    .unwrap();
+                liquidation_limit = liquidation_limit
+                    .checked_add(value.checked_mul(market.liquidation_threshold).unwrap())
+                    .unwrap();
+            }
+
+            let mut debt_value = Decimal::ZERO;
+            for (resource_address, debt_shares) in position.debt_shares.iter() {
+                let market = self.market(*resource_address);
+                debt_value = debt_value
+                    .checked_add(
+                        debt_shares
+                            .checked_mul(market.borrow_index)
+                            .unwrap()
+                            .checked_mul(market.price)
+                            .unwrap(),
+                    )
+                    .unwrap();
+            }
+
+            (borrowing_power, liquidation_limit, debt_value)
+        }
+
+        fn assert_within_loan_to_value(&self, position: &BorrowPosition) {
+            let (borrowing_power, _, debt_value) = self.valuate(position);
+            assert!(
+                debt_value <= borrowing_power,
+                "Debt of {} would exceed the borrowing power of {}",
+                debt_value,
+                borrowing_power
+            );
+        }
+
+        /// Puts as much of the repayment as the position owes in its resource back into the
+        /// market, reducing the debt of the position accordingly, and returns the rest.
+        fn take_repayment(
+            &mut self,
+            position: &mut BorrowPosition,
+            mut repayment: Bucket,
+        ) -> Bucket {
+            let resource_address = repayment.resource_address();
+            let owed_shares = position
+                .debt_shares
+                .get(&resource_address)
+                .copied()
+                .expect("The position owes nothing in this resource");
+
+            let market = self.market_mut(resource_address);
+            let debt = owed_shares.checked_mul(market.borrow_index).unwrap();
+            let (repaid_amount, repaid_shares) = if repayment.amount() >= debt {
+                (debt, owed_shares)
+            } else {
+                (
+                    repayment.amount(),
+                    repayment.amount().checked_div(market.borrow_index).unwrap(),
+                )
+            };
+
+            market.liquidity.put(repayment.take_advanced(
+                repaid_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            ));
+            market.total_debt_shares = market.total_debt_shares.checked_sub(repaid_shares).unwrap();
+
+            if repaid_shares == owed_shares {
+                position.debt_shares.remove(&resource_address);
+            } else {
+                position.debt_shares.insert(
+                    resource_address,
+                    owed_shares.checked_sub(repaid_shares).unwrap(),
+                );
+            }
+
+            repayment
+        }
+
+        /// Checks that the proof is of a single borrow position of this lending market, and
+        /// returns its local id and data.
+        fn check_position(&self, position: Proof) -> (NonFungibleLocalId, BorrowPosition) {
+            let position = position.check(self.position_manager.address());
+            let non_fungible: NonFungible<BorrowPosition> =
+                position.as_non_fungible().non_fungible();
+
+            (non_fungible.local_id().clone(), non_fungible.data())
+        }
+
+        fn save_position(&self, position_id: &NonFungibleLocalId, position: BorrowPosition) {
+            self.position_manager.update_non_fungible_data(
+                position_id,
+                "collateral",
+                position.collateral,
+            );
+            self.position_manager.update_non_fungible_data(
+                position_id,
+                "debt_shares",
+                position.debt_shares,
+            );
+        }
+
+        fn market(&self, resource_address: ResourceAddress) -> &Market {
+            self.markets
+                .get(&resource_address)
+                .expect("No market exists for this resource")
+        }
+
+        fn market_mut(&mut self, resource_address: ResourceAddress) -> &mut Market {
+            self.markets
+                .get_mut(&resource_address)
+                .expect("No market exists for this resource")
+        }
+    }
+}
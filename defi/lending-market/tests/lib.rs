@@ -0,0 +1,356 @@
+use lending_market::PositionOpenedEvent;
+use radix_engine::errors::{ApplicationError, RuntimeError};
+use radix_engine::transaction::TransactionReceipt;
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn borrowers_pay_interest_to_depositors() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which deposits and borrows at the same time
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // A is lent at 0.1% per epoch plus 1% per epoch times the utilization.
+    test_runner.set_current_epoch(Epoch::of(10));
+    let (lending_market, receipts_a, positions, _position_id) = instantiate_lending_market(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        resource_b,
+        (dec!("0.001"), dec!("0.01")),
+    );
+
+    // Test the `borrow` method, half of the 100 A deposited is lent out.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, positions, dec!(1))
+        .pop_from_auth_zone("position")
+        .call_method_with_name_lookup(lending_market, "borrow", |lookup| {
+            (lookup.proof("position"), resource_a, dec!(50))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(950))
+    );
+
+    // Test the `repay` method after 10 epochs at 0.6% per epoch, 53 A are owed and the rest of
+    // the repayment is returned.
+    test_runner.set_current_epoch(Epoch::of(20));
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, positions, dec!(1))
+        .pop_from_auth_zone("position")
+        .withdraw_from_account(account_component, resource_a, dec!(60))
+        .take_all_from_worktop(resource_a, "repayment")
+        .call_method_with_name_lookup(lending_market, "repay", |lookup| {
+            (lookup.proof("position"), lookup.bucket("repayment"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(897))
+    );
+
+    // Test the `withdraw` method, the receipts are now worth the deposit plus the interest paid.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, receipts_a, dec!(100))
+        .take_all_from_worktop(receipts_a, "receipts")
+        .call_method_with_name_lookup(lending_market, "withdraw", |lookup| {
+            (lookup.bucket("receipts"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(1000))
+    );
+}
+
+#[test]
+fn undercollateralized_position_can_be_liquidated() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which borrows and liquidates at the same time
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (lending_market, _receipts_a, positions, position_id) = instantiate_lending_market(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        resource_b,
+        (dec!(0), dec!(0)),
+    );
+
+    // The 100 B of collateral allow borrowing at most 50 A.
+    for (amount, succeeds) in [(dec!(51), false), (dec!(50), true)] {
+        let manifest = ManifestBuilder::new()
+            .create_proof_from_account_of_amount(account_component, positions, dec!(1))
+            .pop_from_auth_zone("position")
+            .call_method_with_name_lookup(lending_market, "borrow", |lookup| {
+                (lookup.proof("position"), resource_a, amount)
+            })
+            .deposit_batch(account_component)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+
+    // The debt of 50 A is below the liquidation threshold of 80% of the collateral.
+    let liquidate = |position_id: NonFungibleLocalId| {
+        ManifestBuilder::new()
+            .withdraw_from_account(account_component, resource_a, dec!(10))
+            .take_all_from_worktop(resource_a, "repayment")
+            .call_method_with_name_lookup(lending_market, "liquidate", |lookup| {
+                (position_id, lookup.bucket("repayment"), resource_b)
+            })
+            .deposit_batch(account_component)
+            .build()
+    };
+    let receipt = test_runner
+        .execute_manifest_ignoring_fee(liquidate(position_id.clone()), vec![badge.clone()]);
+    receipt.expect_commit_failure();
+
+    // Test the `set_price` method, which leaves the collateral worth 60 A.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            lending_market,
+            "set_price",
+            manifest_args!(resource_b, dec!("0.6")),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `liquidate` method, repaying 10 A seizes 10.5 A worth of B.
+    let receipt = test_runner.execute_manifest_ignoring_fee(liquidate(position_id), vec![badge]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        Some(dec!("917.5"))
+    );
+}
+
+#[test]
+fn loan_to_value_and_liquidity_are_enforced() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which deposits and borrows at the same time
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (lending_market, receipts_a, positions, _position_id) = instantiate_lending_market(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        resource_a,
+        resource_b,
+        (dec!(0), dec!(0)),
+    );
+
+    // Test the `borrow` method beyond the 50 A allowed by the 100 B of collateral, and then up to
+    // them.
+    for (amount, succeeds) in [(dec!(51), false), (dec!(50), true)] {
+        let manifest = ManifestBuilder::new()
+            .create_proof_from_account_of_amount(account_component, positions, dec!(1))
+            .pop_from_auth_zone("position")
+            .call_method_with_name_lookup(lending_market, "borrow", |lookup| {
+                (lookup.proof("position"), resource_a, amount)
+            })
+            .deposit_batch(account_component)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_specific_failure(|error| {
+                matches!(
+                    error,
+                    RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                        if message.contains("would exceed the borrowing power")
+                )
+            });
+        }
+    }
+
+    // Test the `remove_collateral` method, the 99 B that would be left only allow borrowing 49.5 A.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, positions, dec!(1))
+        .pop_from_auth_zone("position")
+        .call_method_with_name_lookup(lending_market, "remove_collateral", |lookup| {
+            (lookup.proof("position"), resource_b, dec!(1))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                if message.contains("would exceed the borrowing power")
+        )
+    });
+
+    // Test the `withdraw` method with all the receipts while half of the deposit is lent out, and
+    // then with the receipts of the half that is not.
+    for (amount, succeeds) in [(dec!(100), false), (dec!(50), true)] {
+        let manifest = ManifestBuilder::new()
+            .withdraw_from_account(account_component, receipts_a, amount)
+            .take_all_from_worktop(receipts_a, "receipts")
+            .call_method_with_name_lookup(lending_market, "withdraw", |lookup| {
+                (lookup.bucket("receipts"),)
+            })
+            .deposit_batch(account_component)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_specific_failure(|error| {
+                matches!(
+                    error,
+                    RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                        if message.contains("Not enough liquidity to withdraw")
+                )
+            });
+        }
+    }
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(1000))
+    );
+}
+
+/// Instantiates a lending market with markets for both resources, both priced at 1 with a
+/// loan-to-value of 50% and a liquidation threshold of 80%, and with the given base rate and rate
+/// slope for the first resource. Deposits 100 of the first resource and opens a position with 100
+/// of the second one as collateral. Returns the lending market, the receipt tokens of the first
+/// resource, the resource of the borrow positions and the local id of the position.
+fn instantiate_lending_market(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    resource_a: ResourceAddress,
+    resource_b: ResourceAddress,
+    (base_rate, rate_slope): (Decimal, Decimal),
+) -> (
+    ComponentAddress,
+    ResourceAddress,
+    ResourceAddress,
+    NonFungibleLocalId,
+) {
+    let badge = NonFungibleGlobalId::from_public_key(public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Test the `instantiate_lending_market` function.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "LendingMarket",
+            "instantiate_lending_market",
+            manifest_args!(OwnerRole::Fixed(rule!(require(badge.clone())))),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let commit = receipt.expect_commit_success();
+    let lending_market = commit.new_component_addresses()[0];
+    let positions = commit.new_resource_addresses()[0];
+
+    // Test the `add_market` method.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            lending_market,
+            "add_market",
+            manifest_args!(
+                resource_a,
+                dec!(1),
+                dec!("0.5"),
+                dec!("0.8"),
+                base_rate,
+                rate_slope
+            ),
+        )
+        .call_method(
+            lending_market,
+            "add_market",
+            manifest_args!(
+                resource_b,
+                dec!(1),
+                dec!("0.5"),
+                dec!("0.8"),
+                dec!(0),
+                dec!(0)
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let receipts_a = receipt.expect_commit_success().new_resource_addresses()[0];
+
+    // Test the `deposit` and `open_position` methods.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(100))
+        .take_all_from_worktop(resource_a, "deposit")
+        .call_method_with_name_lookup(lending_market, "deposit", |lookup| {
+            (lookup.bucket("deposit"),)
+        })
+        .withdraw_from_account(account_component, resource_b, dec!(100))
+        .take_all_from_worktop(resource_b, "collateral")
+        .call_method_with_name_lookup(lending_market, "open_position", |lookup| {
+            (lookup.bucket("collateral"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    let position_id =
+        find_event::<PositionOpenedEvent>(test_runner, &receipt, "PositionOpenedEvent").position_id;
+    assert_eq!(
+        test_runner.account_balance(account_component, receipts_a),
+        Some(dec!(100))
+    );
+
+    (lending_market, receipts_a, positions, position_id)
+}
+
+/// Decodes the event of the given name emitted by the transaction, failing if there is none.
+fn find_event<T: ScryptoDecode>(
+    test_runner: &DefaultTestRunner,
+    receipt: &TransactionReceipt,
+    name: &str,
+) -> T {
+    let (_, data) = receipt
+        .expect_commit_success()
+        .application_events
+        .iter()
+        .find(|(event_type_identifier, _)| test_runner.event_name(event_type_identifier) == name)
+        .expect("Event was not emitted");
+
+    scrypto_decode(data).unwrap()
+}
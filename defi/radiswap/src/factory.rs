@@ -0,0 +1,118 @@
+use crate::radiswap::Radiswap;
+use crate::sorted_pair;
+use crate::InstantiationEvent;
+use scrypto::prelude::*;
+
+// Radiswap::new runs as part of this blueprint when called from `create_pool`, so the events that
+// it emits have to be registered on this blueprint as well.
+#[blueprint]
+#[events(PoolCreatedEvent, InstantiationEvent)]
+mod radiswap_factory {
+    /// A RadiswapFactory instantiates Radiswap pools and registers each of them under the pair of
+    /// resources that it trades. At most one pool may exist per pair, which gives indexers and
+    /// routers a single deterministic place to look a pool up.
+    ///
+    /// Anyone may create a pool, but every pool is owned by the owner of the factory. Otherwise
+    /// whoever registered a pair first would own the only pool of that pair, and could pause it or
+    /// raise its fees at will.
+    struct RadiswapFactory {
+        /// The owner role given to every pool created by this factory, which is the owner role of
+        /// the factory itself.
+        owner_role: OwnerRole,
+
+        /// The pools created by this factory, keyed by the sorted pair of the resources that they
+        /// trade.
+        pools_by_pair: KeyValueStore<(ResourceAddress, ResourceAddress), Global<Radiswap>>,
+
+        /// The pools created by this factory, keyed by the order in which they were created. This
+        /// is what allows the list of all pools to be read in pages.
+        pools_by_index: KeyValueStore<u64, Global<Radiswap>>,
+
+        /// The number of pools created by this factory so far.
+        pool_count: u64,
+    }
+
+    impl RadiswapFactory {
+        pub fn instantiate_factory(owner_role: OwnerRole) -> Global<RadiswapFactory> {
+            Self {
+                owner_role: owner_role.clone(),
+                pools_by_pair: KeyValueStore::new(),
+                pools_by_index: KeyValueStore::new(),
+                pool_count: 0,
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role)
+            .globalize()
+        }
+
+        /// Instantiates a new Radiswap pool for the given pair of resources, owned by the owner of
+        /// this factory. Fails if this factory already created a pool for the pair, no matter the
+        /// order in which the resources were given.
+        pub fn create_pool(
+            &mut self,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+            fee_bps: u16,
+        ) -> Global<Radiswap> {
+            let pair = sorted_pair(resource_address1, resource_address2);
+            assert!(
+                self.pools_by_pair.get(&pair).is_none(),
+                "A pool already exists for this pair of resources"
+            );
+
+            // The creator of the pool is not trusted with pausing it, this is left to the owner,
+            // who may appoint a pauser later on.
+            let pool = Radiswap::new(
+                self.owner_role.clone(),
+                rule!(deny_all),
+                resource_address1,
+                resource_address2,
+                fee_bps,
+            );
+
+            self.pools_by_pair.insert(pair, pool.clone());
+            self.pools_by_index.insert(self.pool_count, pool.clone());
+            self.pool_count += 1;
+
+            Runtime::emit_event(PoolCreatedEvent {
+                owner_role: self.owner_role.clone(),
+                resource_address1,
+                resource_address2,
+                component_address: pool.address(),
+            });
+
+            pool
+        }
+
+        pub fn get_pool(
+            &self,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+        ) -> Option<Global<Radiswap>> {
+            self.pools_by_pair
+                .get(&sorted_pair(resource_address1, resource_address2))
+                .map(|pool| pool.clone())
+        }
+
+        /// Returns at most `limit` pools in the order in which they were created, starting from
+        /// the pool at index `start`.
+        pub fn all_pools(&self, start: u64, limit: u64) -> Vec<Global<Radiswap>> {
+            let end = cmp::min(start.saturating_add(limit), self.pool_count);
+            (start..end)
+                .map(|index| self.pools_by_index.get(&index).unwrap().clone())
+                .collect()
+        }
+
+        pub fn pool_count(&self) -> u64 {
+            self.pool_count
+        }
+    }
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct PoolCreatedEvent {
+    pub owner_role: OwnerRole,
+    pub resource_address1: ResourceAddress,
+    pub resource_address2: ResourceAddress,
+    pub component_address: ComponentAddress,
+}
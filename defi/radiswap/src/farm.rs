@@ -0,0 +1,267 @@
+use scrypto::prelude::*;
+
+/// A stake in a RadiswapFarm. The rewards owed to the staker are the staked amount times the
+/// rewards per pool unit accrued by the farm, minus the `reward_debt` which accounts for rewards
+/// accrued before the stake was made or already claimed.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct StakePosition {
+    pub staked_amount: Decimal,
+    #[mutable]
+    pub reward_debt: Decimal,
+}
+
+#[blueprint]
+mod radiswap_farm {
+    enable_method_auth! {
+        methods {
+            stake => PUBLIC;
+            unstake => PUBLIC;
+            claim_rewards => PUBLIC;
+            deposit_rewards => PUBLIC;
+            set_reward_rate => restrict_to: [OWNER];
+            get_pending_rewards => PUBLIC;
+            get_reward_rate => PUBLIC;
+        }
+    }
+
+    /// A RadiswapFarm rewards the holders of the pool units of a Radiswap pool for staking them.
+    /// Rewards are paid out at a fixed rate per epoch, shared between the stakers in proportion
+    /// to the pool units they have staked.
+    ///
+    /// Rather than crediting every staker on every epoch, the farm keeps a running total of the
+    /// rewards accrued per staked pool unit. A staker is owed the difference between this total
+    /// now and when they staked, which keeps the cost of every operation independent of the number
+    /// of stakers.
+    struct RadiswapFarm {
+        /// The vault holding the staked pool units.
+        staked_pool_units: Vault,
+        /// The vault holding the rewards, both those which have accrued to stakers and those which
+        /// have yet to.
+        rewards: Vault,
+        /// The resource manager of the stake position NFTs given out to stakers.
+        stake_position_manager: ResourceManager,
+        /// The amount of rewards paid out to all stakers combined on every epoch.
+        reward_rate: Decimal,
+        /// The rewards accrued by a single staked pool unit since the farm was instantiated.
+        rewards_per_pool_unit: Decimal,
+        /// The rewards which have accrued to stakers but have not been claimed yet. These are held
+        /// in the rewards vault and are never handed out to anyone else.
+        accrued_rewards: Decimal,
+        /// The epoch up to which rewards have been accrued.
+        last_update_epoch: Epoch,
+    }
+
+    impl RadiswapFarm {
+        /// Instantiates a farm which pays out `reward_rate` of the reward resource per epoch to
+        /// the stakers of the given pool units. The farm starts with no rewards, they have to be
+        /// deposited through `deposit_rewards`.
+        pub fn instantiate_farm(
+            owner_role: OwnerRole,
+            pool_units_resource_address: ResourceAddress,
+            reward_resource_address: ResourceAddress,
+            reward_rate: Decimal,
+        ) -> Global<RadiswapFarm> {
+            assert!(
+                reward_rate >= Decimal::ZERO,
+                "Reward rate must not be negative"
+            );
+
+            let (address_reservation, component_address) =
+                Runtime::allocate_component_address(RadiswapFarm::blueprint_id());
+
+            let stake_position_manager = ResourceBuilder::new_ruid_non_fungible::<StakePosition>(
+                OwnerRole::None,
+            )
+            .metadata(metadata!(
+                init {
+                    "name" => "Radiswap Farm Stake Position".to_owned(), locked;
+                }
+            ))
+            .mint_roles(mint_roles!(
+                minter => rule!(require(global_caller(component_address)));
+                minter_updater => rule!(deny_all);
+            ))
+            .burn_roles(burn_roles!(
+                burner => rule!(require(global_caller(component_address)));
+                burner_updater => rule!(deny_all);
+            ))
+            .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                non_fungible_data_updater => rule!(require(global_caller(component_address)));
+                non_fungible_data_updater_updater => rule!(deny_all);
+            ))
+            .create_with_no_initial_supply();
+
+            Self {
+                staked_pool_units: Vault::new(pool_units_resource_address),
+                rewards: Vault::new(reward_resource_address),
+                stake_position_manager,
+                reward_rate,
+                rewards_per_pool_unit: Decimal::ZERO,
+                accrued_rewards: Decimal::ZERO,
+                last_update_epoch: Runtime::current_epoch(),
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role)
+            .with_address(address_reservation)
+            .globalize()
+        }
+
+        /// Stakes the given pool units and returns a stake position NFT, which is needed to claim
+        /// the rewards and to unstake the pool units later on.
+        pub fn stake(&mut self, pool_units: Bucket) -> Bucket {
+            assert!(
+                !pool_units.is_empty(),
+                "At least some pool units must be staked"
+            );
+            self.accrue_rewards();
+
+            let staked_amount = pool_units.amount();
+            self.staked_pool_units.put(pool_units);
+
+            self.stake_position_manager
+                .mint_ruid_non_fungible(StakePosition {
+                    staked_amount,
+                    reward_debt: self.reward_debt_of(staked_amount),
+                })
+        }
+
+        /// Burns the stake position and returns the staked pool units along with all the rewards
+        /// that the position was still owed.
+        pub fn unstake(&mut self, stake_position: Bucket) -> (Bucket, Bucket) {
+            assert_eq!(
+                stake_position.resource_address(),
+                self.stake_position_manager.address(),
+                "Incorrect resource passed in for the stake position"
+            );
+            self.accrue_rewards();
+
+            let position: StakePosition = stake_position.as_non_fungible().non_fungible().data();
+            let rewards = self.take_pending_rewards(&position);
+            let pool_units = self.staked_pool_units.take(position.staked_amount);
+
+            stake_position.burn();
+
+            (pool_units, rewards)
+        }
+
+        /// Returns the rewards owed to the presented stake position, which keeps its pool units
+        /// staked.
+        pub fn claim_rewards(&mut self, stake_position: Proof) -> Bucket {
+            let stake_position = stake_position.check(self.stake_position_manager.address());
+            self.accrue_rewards();
+
+            let non_fungible: NonFungible<StakePosition> =
+                stake_position.as_non_fungible().non_fungible();
+            let position = non_fungible.data();
+            let rewards = self.take_pending_rewards(&position);
+
+            self.stake_position_manager.update_non_fungible_data(
+                non_fungible.local_id(),
+                "reward_debt",
+                self.reward_debt_of(position.staked_amount),
+            );
+
+            rewards
+        }
+
+        /// Tops up the rewards of the farm. Anyone may deposit rewards, they are paid out to the
+        /// stakers at the reward rate.
+        pub fn deposit_rewards(&mut self, rewards: Bucket) {
+            self.accrue_rewards();
+            self.rewards.put(rewards);
+        }
+
+        /// Changes the amount of rewards paid out per epoch. Rewards accrued at the previous rate
+        /// up to the current epoch are kept.
+        pub fn set_reward_rate(&mut self, reward_rate: Decimal) {
+            assert!(
+                reward_rate >= Decimal::ZERO,
+                "Reward rate must not be negative"
+            );
+            self.accrue_rewards();
+            self.reward_rate = reward_rate;
+        }
+
+        pub fn get_pending_rewards(&self, stake_position_id: NonFungibleLocalId) -> Decimal {
+            let position: StakePosition = self
+                .stake_position_manager
+                .get_non_fungible_data(&stake_position_id);
+            let (rewards_per_pool_unit, _) = self.calculate_accrual();
+
+            position
+                .staked_amount
+                .checked_mul(rewards_per_pool_unit)
+                .unwrap()
+                .checked_sub(position.reward_debt)
+                .unwrap()
+        }
+
+        pub fn get_reward_rate(&self) -> Decimal {
+            self.reward_rate
+        }
+
+        /// Brings the rewards per pool unit up to date with the current epoch.
+        fn accrue_rewards(&mut self) {
+            let (rewards_per_pool_unit, accrued_rewards) = self.calculate_accrual();
+            self.rewards_per_pool_unit = rewards_per_pool_unit;
+            self.accrued_rewards = accrued_rewards;
+            self.last_update_epoch = Runtime::current_epoch();
+        }
+
+        /// Returns what the rewards per pool unit and the accrued rewards would be if rewards were
+        /// accrued up to the current epoch. The rewards paid out are capped to what is left in the
+        /// rewards vault, and nothing is paid out while no pool units are staked.
+        fn calculate_accrual(&self) -> (Decimal, Decimal) {
+            let total_staked = self.staked_pool_units.amount();
+            let elapsed_epochs =
+                Runtime::current_epoch().number() - self.last_update_epoch.number();
+            if elapsed_epochs == 0 || total_staked.is_zero() {
+                return (self.rewards_per_pool_unit, self.accrued_rewards);
+            }
+
+            let unallocated_rewards = self
+                .rewards
+                .amount()
+                .checked_sub(self.accrued_rewards)
+                .unwrap();
+            let new_rewards = cmp::min(
+                self.reward_rate.checked_mul(elapsed_epochs).unwrap(),
+                unallocated_rewards,
+            );
+
+            (
+                self.rewards_per_pool_unit
+                    .checked_add(new_rewards.checked_div(total_staked).unwrap())
+                    .unwrap(),
+                self.accrued_rewards.checked_add(new_rewards).unwrap(),
+            )
+        }
+
+        fn reward_debt_of(&self, staked_amount: Decimal) -> Decimal {
+            staked_amount
+                .checked_mul(self.rewards_per_pool_unit)
+                .unwrap()
+        }
+
+        /// Takes the rewards owed to the given position out of the rewards vault. The rewards per
+        /// pool unit must have been accrued up to the current epoch beforehand.
+        fn take_pending_rewards(&mut self, position: &StakePosition) -> Bucket {
+            let pending_rewards = self
+                .reward_debt_of(position.staked_amount)
+                .checked_sub(position.reward_debt)
+                .unwrap();
+            // Rounding down the rewards per pool unit means that the sum of what every staker is
+            // owed never exceeds the accrued rewards.
+            let pending_rewards = cmp::min(pending_rewards, self.accrued_rewards);
+            // The reward resource may have fewer decimal places than the rewards owed, so only
+            // what could actually be taken is no longer accrued.
+            let rewards = self.rewards.take_advanced(
+                pending_rewards,
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            );
+            self.accrued_rewards = self.accrued_rewards.checked_sub(rewards.amount()).unwrap();
+
+            rewards
+        }
+    }
+}
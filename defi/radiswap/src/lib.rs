@@ -1,17 +1,218 @@
 use scrypto::prelude::*;
 
+/// A factory blueprint which instantiates Radiswap pools and keeps a registry of them by pair.
+mod factory;
+/// A farm blueprint which rewards the stakers of Radiswap pool units with a reward resource.
+mod farm;
+/// A limit order book blueprint whose orders are filled against a Radiswap pool by keepers.
+mod limit_order;
+/// A router blueprint which swaps along a path of several Radiswap pools in a single call.
+mod router;
+/// The math of the StableSwap invariant used by Radiswap pools of pegged resources.
+mod stableswap;
+
+pub use limit_order::{OrderPlacedEvent, OrderTrigger};
+
+/// The number of basis points in one whole, used to convert the fee from basis points into a
+/// fraction.
+const BASIS_POINTS: u16 = 10_000;
+
+/// The highest fee that a Radiswap pool may charge on swaps, 10%. Neither the instantiator nor the
+/// owner can go above this value.
+const MAX_FEE_BPS: u16 = 1_000;
+
+/// The highest share of the swap fee that the owner may divert to the protocol, 50%, in basis
+/// points of the fee. The rest of the fee always goes to the liquidity providers.
+const MAX_PROTOCOL_FEE_SHARE_BPS: u16 = 5_000;
+
+/// The highest amplification coefficient that a StableSwap Radiswap pool may be instantiated with.
+const MAX_AMPLIFICATION: u64 = 1_000_000;
+
+/// The number of price observations kept by a pool. At most one observation is recorded per epoch,
+/// so this bounds how far back `observe` can look.
+const MAX_PRICE_OBSERVATIONS: usize = 256;
+
+/// The most bisection steps taken when searching for the part of a zap to swap. Each step halves
+/// the search interval, so this is enough to get down to the last atto of any realistic amount.
+const MAX_ZAP_ITERATIONS: usize = 128;
+
+/// A snapshot of the cumulative prices of a pool at the given epoch. The cumulative price of a
+/// resource is the sum, over every epoch elapsed since the pool was created, of the price of that
+/// resource in terms of the other resource of the pool.
+#[derive(ScryptoSbor, Clone)]
+pub struct PriceObservation {
+    pub epoch: Epoch,
+    pub price_cumulative1: Decimal,
+    pub price_cumulative2: Decimal,
+}
+
+/// Orders a pair of resource addresses so that a pair is always stored under the same key,
+/// regardless of the order in which its resources are given.
+fn sorted_pair(
+    resource_address1: ResourceAddress,
+    resource_address2: ResourceAddress,
+) -> (ResourceAddress, ResourceAddress) {
+    if resource_address1 <= resource_address2 {
+        (resource_address1, resource_address2)
+    } else {
+        (resource_address2, resource_address1)
+    }
+}
+
+/// The terms of an outstanding flash swap. A receipt carrying these terms is minted by
+/// `flash_swap` and can never be deposited, so the transaction can only succeed if the receipt is
+/// given back to `repay_flash_swap` and burned.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct FlashSwapDue {
+    pub resource_address: ResourceAddress,
+    pub amount: Decimal,
+    /// The reserves of the pool, in the order of the pool's vaults, before the flash swap.
+    pub reserves_before: (Decimal, Decimal),
+}
+
+/// The invariant that a Radiswap pool prices its swaps with.
+#[derive(ScryptoSbor, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// The product of the reserves is kept constant, suitable for any pair of resources.
+    ConstantProduct,
+    /// The StableSwap invariant with the given amplification coefficient, suitable for pairs of
+    /// resources that are pegged to each other.
+    StableSwap { amplification: Decimal },
+}
+
 #[blueprint]
+#[events(InstantiationEvent, AddLiquidityEvent, RemoveLiquidityEvent, SwapEvent)]
 mod radiswap {
+    enable_method_auth! {
+        roles {
+            pauser => updatable_by: [OWNER];
+        },
+        methods {
+            add_liquidity => PUBLIC;
+            remove_liquidity => PUBLIC;
+            swap => PUBLIC;
+            swap_exact_input => PUBLIC;
+            swap_exact_output => PUBLIC;
+            zap_in => PUBLIC;
+            zap_out => PUBLIC;
+            get_amount_out => PUBLIC;
+            get_amount_in => PUBLIC;
+            spot_price => PUBLIC;
+            price_impact => PUBLIC;
+            get_resource_addresses => PUBLIC;
+            observe => PUBLIC;
+            flash_swap => PUBLIC;
+            repay_flash_swap => PUBLIC;
+            get_curve => PUBLIC;
+            get_fee => PUBLIC;
+            set_fee => restrict_to: [OWNER];
+            get_protocol_fee_share => PUBLIC;
+            set_protocol_fee_share => restrict_to: [OWNER];
+            collect_protocol_fees => restrict_to: [OWNER];
+            pause => restrict_to: [pauser, OWNER];
+            unpause => restrict_to: [pauser, OWNER];
+            set_max_price_move => restrict_to: [OWNER];
+            get_max_price_move => PUBLIC;
+        }
+    }
+
     struct Radiswap {
         pool_component: Global<TwoResourcePool>,
+        /// The fee charged on the input of every swap, in basis points. Apart from the protocol's
+        /// share, the fee is never taken out of the pool, it stays in the pool's vaults and thus
+        /// increases the redemption value of the pool units.
+        fee_bps: u16,
+        /// The share of the swap fee diverted to the protocol, in basis points of the fee. This is
+        /// zero unless the owner turns it on.
+        protocol_fee_share_bps: u16,
+        /// The protocol's share of the swap fees, one vault per resource of the pool, held until
+        /// the owner collects them.
+        protocol_fees: HashMap<ResourceAddress, Vault>,
+        /// The invariant used to price swaps, chosen at instantiation.
+        curve: Curve,
+        /// The most recent price observations of the pool, oldest first. The last observation is
+        /// always the one the cumulative prices are accumulated from.
+        price_observations: Vec<PriceObservation>,
+        /// The resource manager of the non-depositable flash swap receipts.
+        flash_swap_receipt_manager: ResourceManager,
+        /// Whether a flash swap has been taken out and not yet repaid. The pool refuses any other
+        /// operation until it is, so that the repayment is checked against the reserves the pool
+        /// had when the flash swap was taken out.
+        flash_swap_in_progress: bool,
+        /// Whether the pool has been paused by the pauser or the owner. A paused pool refuses
+        /// swaps and new liquidity, but liquidity can always be removed from it.
+        paused: bool,
+        /// The epoch of the first swap of the current epoch, along with the spot price of the first
+        /// resource of the pool before that swap. The circuit breaker measures price moves
+        /// against this price.
+        epoch_open_price: (Epoch, Decimal),
+        /// The largest move of the spot price, in basis points of the price at the start of the
+        /// epoch, that swaps may cause within one epoch. Swaps that would move the price further
+        /// are rejected. The circuit breaker is off when this is `None`.
+        max_price_move_bps: Option<u16>,
     }
 
     impl Radiswap {
+        /// Instantiates a pool which prices swaps with the constant-product invariant. Besides the
+        /// owner, whoever satisfies `pauser_rule` may pause and unpause the pool.
         pub fn new(
             owner_role: OwnerRole,
+            pauser_rule: AccessRule,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+            fee_bps: u16,
+        ) -> Global<Radiswap> {
+            Self::instantiate_with_curve(
+                owner_role,
+                pauser_rule,
+                resource_address1,
+                resource_address2,
+                fee_bps,
+                Curve::ConstantProduct,
+            )
+        }
+
+        /// Instantiates a pool which prices swaps with the StableSwap invariant, for resources
+        /// that are expected to trade close to one for one. The higher the amplification, the
+        /// lower the slippage near the peg.
+        pub fn new_stable(
+            owner_role: OwnerRole,
+            pauser_rule: AccessRule,
             resource_address1: ResourceAddress,
             resource_address2: ResourceAddress,
+            fee_bps: u16,
+            amplification: Decimal,
         ) -> Global<Radiswap> {
+            assert!(
+                amplification >= Decimal::ONE && amplification <= Decimal::from(MAX_AMPLIFICATION),
+                "Amplification must be between 1 and {}",
+                MAX_AMPLIFICATION
+            );
+
+            Self::instantiate_with_curve(
+                owner_role,
+                pauser_rule,
+                resource_address1,
+                resource_address2,
+                fee_bps,
+                Curve::StableSwap { amplification },
+            )
+        }
+
+        fn instantiate_with_curve(
+            owner_role: OwnerRole,
+            pauser_rule: AccessRule,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+            fee_bps: u16,
+            curve: Curve,
+        ) -> Global<Radiswap> {
+            assert!(
+                fee_bps <= MAX_FEE_BPS,
+                "Fee must not exceed {} basis points",
+                MAX_FEE_BPS
+            );
+
             let (address_reservation, component_address) =
                 Runtime::allocate_component_address(Radiswap::blueprint_id());
             let global_component_caller_badge =
@@ -27,11 +228,66 @@ mod radiswap {
                 None,
             );
 
-            Self { pool_component }
-                .instantiate()
-                .prepare_to_globalize(owner_role)
-                .with_address(address_reservation)
-                .globalize()
+            // Define a "transient" resource which can never be deposited once created, only burned
+            let flash_swap_receipt_manager =
+                ResourceBuilder::new_ruid_non_fungible::<FlashSwapDue>(OwnerRole::None)
+                    .metadata(metadata!(
+                        init {
+                            "name" =>
+                            "Flash swap receipt for Radiswap - must be returned to be burned!".to_owned(), locked;
+                        }
+                    ))
+                    .mint_roles(mint_roles!(
+                        minter => rule!(require(global_caller(component_address)));
+                        minter_updater => rule!(deny_all);
+                    ))
+                    .burn_roles(burn_roles!(
+                        burner => rule!(require(global_caller(component_address)));
+                        burner_updater => rule!(deny_all);
+                    ))
+                    .deposit_roles(deposit_roles!(
+                        depositor => rule!(deny_all);
+                        depositor_updater => rule!(deny_all);
+                    ))
+                    .create_with_no_initial_supply();
+
+            let mut protocol_fees = HashMap::new();
+            protocol_fees.insert(resource_address1, Vault::new(resource_address1));
+            protocol_fees.insert(resource_address2, Vault::new(resource_address2));
+
+            let component = Self {
+                pool_component,
+                fee_bps,
+                protocol_fee_share_bps: 0,
+                protocol_fees,
+                curve,
+                price_observations: vec![PriceObservation {
+                    epoch: Runtime::current_epoch(),
+                    price_cumulative1: Decimal::ZERO,
+                    price_cumulative2: Decimal::ZERO,
+                }],
+                flash_swap_receipt_manager,
+                flash_swap_in_progress: false,
+                paused: false,
+                epoch_open_price: (Runtime::current_epoch(), Decimal::ZERO),
+                max_price_move_bps: None,
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role.clone())
+            .roles(roles!(
+                pauser => pauser_rule;
+            ))
+            .with_address(address_reservation)
+            .globalize();
+
+            Runtime::emit_event(InstantiationEvent {
+                component_address: component.address(),
+                resource_address1,
+                resource_address2,
+                owner_role,
+            });
+
+            component
         }
 
         pub fn add_liquidity(
@@ -39,10 +295,35 @@ mod radiswap {
             resource1: Bucket,
             resource2: Bucket,
         ) -> (Bucket, Option<Bucket>) {
+            self.assert_not_paused();
+            self.assert_no_flash_swap_in_progress();
+            self.update_price_observations();
+
+            let mut contributed_resources = [
+                (resource1.resource_address(), resource1.amount()),
+                (resource2.resource_address(), resource2.amount()),
+            ];
+
             // All the checks for correctness of buckets and everything else is handled by the pool
             // component! Just pass it the resources and it will either return the pool units back
             // if it succeeds or abort on failure.
-            self.pool_component.contribute((resource1, resource2))
+            let (pool_units, change) = self.pool_component.contribute((resource1, resource2));
+
+            // Whatever the pool did not need is given back and was not contributed.
+            if let Some(change) = &change {
+                for (resource_address, amount) in contributed_resources.iter_mut() {
+                    if *resource_address == change.resource_address() {
+                        *amount = amount.checked_sub(change.amount()).unwrap();
+                    }
+                }
+            }
+
+            Runtime::emit_event(AddLiquidityEvent {
+                contributed_resources,
+                pool_units_amount: pool_units.amount(),
+            });
+
+            (pool_units, change)
         }
 
         /// This method does not need to be here - the pool units are redeemable without it by the
@@ -50,30 +331,810 @@ mod radiswap {
         /// so that users are only interacting with one component and do not need to know about the
         /// address of Radiswap and the address of the Radiswap pool.
         pub fn remove_liquidity(&mut self, pool_units: Bucket) -> (Bucket, Bucket) {
-            self.pool_component.redeem(pool_units)
+            self.assert_no_flash_swap_in_progress();
+            self.update_price_observations();
+
+            let pool_units_amount = pool_units.amount();
+            let (bucket1, bucket2) = self.pool_component.redeem(pool_units);
+
+            Runtime::emit_event(RemoveLiquidityEvent {
+                pool_units_amount,
+                redeemed_resources: [
+                    (bucket1.resource_address(), bucket1.amount()),
+                    (bucket2.resource_address(), bucket2.amount()),
+                ],
+            });
+
+            (bucket1, bucket2)
         }
 
-        pub fn swap(&mut self, input_bucket: Bucket) -> Bucket {
-            let mut reserves = self.vault_reserves();
+        pub fn swap(&mut self, mut input_bucket: Bucket) -> Bucket {
+            self.assert_not_paused();
+            self.assert_no_flash_swap_in_progress();
+            self.update_price_observations();
+            self.record_epoch_open_price();
 
             let input_amount = input_bucket.amount();
 
-            let input_reserves = reserves
-                .remove(&input_bucket.resource_address())
-                .expect("Resource does not belong to the pool");
-            let (output_resource_address, output_reserves) = reserves.into_iter().next().unwrap();
+            let (input_reserves, output_resource_address, output_reserves) =
+                self.reserves_for(input_bucket.resource_address());
 
-            let output_amount = input_amount
-                .checked_mul(output_reserves)
-                .unwrap()
-                .checked_div(input_reserves.checked_add(input_amount).unwrap())
-                .unwrap();
+            let output_amount =
+                self.calculate_output_amount(input_amount, input_reserves, output_reserves);
 
             // NOTE: It's the responsibility of the user of the pool to do the appropriate rounding
             // before calling the withdraw method.
 
+            // The whole input, fee included, goes into the pool. Only the output is computed on the
+            // input net of the fee, so the fee is left behind for the liquidity providers, minus the
+            // protocol's share of it.
+            let input_resource_address = input_bucket.resource_address();
+            let (lp_fee, protocol_fee) = self.take_protocol_fee(&mut input_bucket);
             self.deposit(input_bucket);
-            self.withdraw(output_resource_address, output_amount)
+            let output_bucket = self.withdraw(output_resource_address, output_amount);
+            self.assert_price_move_within_limit();
+
+            Runtime::emit_event(SwapEvent {
+                input: (input_resource_address, input_amount),
+                output: (output_resource_address, output_bucket.amount()),
+                lp_fee,
+                protocol_fee,
+            });
+
+            output_bucket
+        }
+
+        /// Swaps the whole input bucket, failing if the output would be less than `min_output` or
+        /// if the transaction is committed after `deadline_epoch`. This protects the caller from
+        /// the reserves moving between the moment they got a quote and the moment the transaction
+        /// is executed.
+        pub fn swap_exact_input(
+            &mut self,
+            input_bucket: Bucket,
+            min_output: Decimal,
+            deadline_epoch: Epoch,
+        ) -> Bucket {
+            Self::assert_deadline_not_passed(deadline_epoch);
+
+            let output_bucket = self.swap(input_bucket);
+            assert!(
+                output_bucket.amount() >= min_output,
+                "Output amount of {} is below the minimum output of {}",
+                output_bucket.amount(),
+                min_output
+            );
+
+            output_bucket
+        }
+
+        /// Swaps just enough of the input bucket to receive exactly `desired_output` of the other
+        /// resource, failing if the input bucket is not enough or if the transaction is committed
+        /// after `deadline_epoch`. The unused part of the input is returned along with the output.
+        pub fn swap_exact_output(
+            &mut self,
+            mut input_bucket: Bucket,
+            desired_output: Decimal,
+            deadline_epoch: Epoch,
+        ) -> (Bucket, Bucket) {
+            Self::assert_deadline_not_passed(deadline_epoch);
+            assert!(
+                desired_output > Decimal::ZERO,
+                "Desired output must be positive"
+            );
+            self.assert_not_paused();
+            self.assert_no_flash_swap_in_progress();
+            self.update_price_observations();
+            self.record_epoch_open_price();
+
+            let (input_reserves, output_resource_address, output_reserves) =
+                self.reserves_for(input_bucket.resource_address());
+            let input_amount =
+                self.calculate_input_amount(desired_output, input_reserves, output_reserves);
+            assert!(
+                input_amount <= input_bucket.amount(),
+                "Input of {} is not enough, {} is required for the desired output",
+                input_bucket.amount(),
+                input_amount
+            );
+
+            // The input is rounded up to the divisibility of its resource, in favor of the pool.
+            let mut input_used = input_bucket.take_advanced(
+                input_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            );
+            let input_amount = input_used.amount();
+            let (lp_fee, protocol_fee) = self.take_protocol_fee(&mut input_used);
+            self.deposit(input_used);
+            let output_bucket = self.withdraw(output_resource_address, desired_output);
+            self.assert_price_move_within_limit();
+
+            Runtime::emit_event(SwapEvent {
+                input: (input_bucket.resource_address(), input_amount),
+                output: (output_resource_address, desired_output),
+                lp_fee,
+                protocol_fee,
+            });
+
+            (output_bucket, input_bucket)
+        }
+
+        /// Provides liquidity with a single resource: swaps just enough of the input bucket into
+        /// the other resource for the two to match the ratio of the pool, then contributes both.
+        /// Fails if fewer than `min_pool_units` would be received. Whatever could not be
+        /// contributed because of rounding is returned along with the pool units.
+        pub fn zap_in(
+            &mut self,
+            mut input_bucket: Bucket,
+            min_pool_units: Decimal,
+        ) -> (Bucket, Option<Bucket>) {
+            let (input_reserves, _, output_reserves) =
+                self.reserves_for(input_bucket.resource_address());
+            assert!(
+                !input_reserves.is_zero() && !output_reserves.is_zero(),
+                "Cannot zap into a pool without liquidity"
+            );
+
+            let swap_amount = self.calculate_zap_swap_amount(
+                input_bucket.amount(),
+                input_reserves,
+                output_reserves,
+            );
+            let swap_bucket = input_bucket
+                .take_advanced(swap_amount, WithdrawStrategy::Rounded(RoundingMode::ToZero));
+            let other_bucket = self.swap(swap_bucket);
+
+            let (pool_units, change) = self.add_liquidity(input_bucket, other_bucket);
+            assert!(
+                pool_units.amount() >= min_pool_units,
+                "Pool units amount of {} is below the minimum of {}",
+                pool_units.amount(),
+                min_pool_units
+            );
+
+            (pool_units, change)
+        }
+
+        /// Removes liquidity into a single resource: redeems the pool units and swaps the other
+        /// resource of the pool into `desired_resource`, failing if the total output is less than
+        /// `min_output`.
+        pub fn zap_out(
+            &mut self,
+            pool_units: Bucket,
+            desired_resource: ResourceAddress,
+            min_output: Decimal,
+        ) -> Bucket {
+            let (bucket1, bucket2) = self.remove_liquidity(pool_units);
+            let (mut desired_bucket, other_bucket) =
+                if bucket1.resource_address() == desired_resource {
+                    (bucket1, bucket2)
+                } else if bucket2.resource_address() == desired_resource {
+                    (bucket2, bucket1)
+                } else {
+                    panic!("Resource does not belong to the pool")
+                };
+
+            if !other_bucket.is_empty() {
+                desired_bucket.put(self.swap(other_bucket));
+            } else {
+                other_bucket.drop_empty();
+            }
+            assert!(
+                desired_bucket.amount() >= min_output,
+                "Output amount of {} is below the minimum output of {}",
+                desired_bucket.amount(),
+                min_output
+            );
+
+            desired_bucket
+        }
+
+        /// Returns the amount of the other resource that `swap` would currently give for
+        /// `input_amount` of `input_resource`.
+        pub fn get_amount_out(
+            &self,
+            input_resource: ResourceAddress,
+            input_amount: Decimal,
+        ) -> Decimal {
+            let (input_reserves, _, output_reserves) = self.reserves_for(input_resource);
+            self.calculate_output_amount(input_amount, input_reserves, output_reserves)
+        }
+
+        /// Returns the amount of the other resource that `swap_exact_output` would currently take
+        /// to give `output_amount` of `output_resource`.
+        pub fn get_amount_in(
+            &self,
+            output_resource: ResourceAddress,
+            output_amount: Decimal,
+        ) -> Decimal {
+            let (output_reserves, _, input_reserves) = self.reserves_for(output_resource);
+            self.calculate_input_amount(output_amount, input_reserves, output_reserves)
+        }
+
+        /// Returns the marginal price of `resource` expressed in the other resource of the pool,
+        /// ignoring the fee.
+        pub fn spot_price(&self, resource: ResourceAddress) -> Decimal {
+            let (reserves, _, other_reserves) = self.reserves_for(resource);
+            self.calculate_spot_price(reserves, other_reserves)
+        }
+
+        /// Returns how much worse than the spot price a swap of `input_amount` of `input_resource`
+        /// would execute, as a fraction between 0 and 1. The fee is included in the impact.
+        pub fn price_impact(
+            &self,
+            input_resource: ResourceAddress,
+            input_amount: Decimal,
+        ) -> Decimal {
+            assert!(
+                input_amount > Decimal::ZERO,
+                "Input amount must be positive"
+            );
+
+            let output_amount = self.get_amount_out(input_resource, input_amount);
+            let execution_price = output_amount.checked_div(input_amount).unwrap();
+
+            Decimal::ONE
+                .checked_sub(
+                    execution_price
+                        .checked_div(self.spot_price(input_resource))
+                        .unwrap(),
+                )
+                .unwrap()
+        }
+
+        /// Returns the addresses of the two resources traded by this pool.
+        pub fn get_resource_addresses(&self) -> (ResourceAddress, ResourceAddress) {
+            let mut resource_addresses = self.vault_reserves().into_keys();
+            (
+                resource_addresses.next().unwrap(),
+                resource_addresses.next().unwrap(),
+            )
+        }
+
+        /// Returns the time-weighted average price of each resource of the pool, expressed in the
+        /// other resource, over at least the last `window` epochs. The average is taken from the
+        /// most recent observation that is at least `window` epochs old, so it cannot be moved by
+        /// trades made within a single transaction or epoch the way the spot price can.
+        pub fn observe(&self, window: u64) -> IndexMap<ResourceAddress, Decimal> {
+            assert!(window > 0, "Window must be at least one epoch");
+
+            let current_epoch = Runtime::current_epoch();
+            let target_epoch = current_epoch
+                .number()
+                .checked_sub(window)
+                .expect("Window goes back further than the first epoch");
+            let observation = self
+                .price_observations
+                .iter()
+                .rev()
+                .find(|observation| observation.epoch.number() <= target_epoch)
+                .expect("Not enough price history for this window");
+
+            let current = self.price_observation_at(current_epoch);
+            let elapsed_epochs = current_epoch.number() - observation.epoch.number();
+            let average_prices = [
+                current
+                    .price_cumulative1
+                    .checked_sub(observation.price_cumulative1)
+                    .unwrap()
+                    .checked_div(elapsed_epochs)
+                    .unwrap(),
+                current
+                    .price_cumulative2
+                    .checked_sub(observation.price_cumulative2)
+                    .unwrap()
+                    .checked_div(elapsed_epochs)
+                    .unwrap(),
+            ];
+
+            self.vault_reserves()
+                .into_keys()
+                .zip(average_prices)
+                .collect()
+        }
+
+        /// Lends `amount` of `resource_address` out of the pool along with a receipt which can never
+        /// be deposited. The caller may do anything with the borrowed resource, as long as the
+        /// receipt is given back to `repay_flash_swap` before the end of the transaction together
+        /// with a repayment in either resource of the pool.
+        pub fn flash_swap(
+            &mut self,
+            resource_address: ResourceAddress,
+            amount: Decimal,
+        ) -> (Bucket, Bucket) {
+            self.assert_not_paused();
+            self.assert_no_flash_swap_in_progress();
+            self.update_price_observations();
+            self.record_epoch_open_price();
+
+            let reserves_before: Vec<Decimal> = self.vault_reserves().into_values().collect();
+            let (resource_reserves, _, _) = self.reserves_for(resource_address);
+            assert!(
+                amount > Decimal::ZERO && amount < resource_reserves,
+                "Flash swap amount must be positive and below the reserves of the pool"
+            );
+
+            // Mint an NFT with the flash swap terms. Remember that this resource forbids ever being
+            // deposited in any vault, so the only way for the transaction to complete is for it to be
+            // burned by repay_flash_swap().
+            let flash_swap_receipt =
+                self.flash_swap_receipt_manager
+                    .mint_ruid_non_fungible(FlashSwapDue {
+                        resource_address,
+                        amount,
+                        reserves_before: (reserves_before[0], reserves_before[1]),
+                    });
+            self.flash_swap_in_progress = true;
+
+            (self.withdraw(resource_address, amount), flash_swap_receipt)
+        }
+
+        /// Settles a flash swap. The repayment may be made in either resource of the pool, as long
+        /// as the reserves before the flash swap, less what was lent and plus the repayment net of
+        /// the fee, have an invariant at least as large as the reserves before the flash swap. The pool refuses
+        /// every other operation until the flash swap is settled, so the resource to repay with
+        /// has to be obtained elsewhere, not by swapping in this pool.
+        pub fn repay_flash_swap(&mut self, repayment: Bucket, flash_swap_receipt: Bucket) {
+            assert_eq!(
+                flash_swap_receipt.resource_address(),
+                self.flash_swap_receipt_manager.address(),
+                "Incorrect resource passed in for the flash swap receipt"
+            );
+            let terms: FlashSwapDue = flash_swap_receipt.as_non_fungible().non_fungible().data();
+            let repayment_resource = repayment.resource_address();
+            let repayment_amount = repayment.amount();
+
+            // The pool will refuse the repayment if it is not one of its two resources.
+            self.deposit(repayment);
+
+            // The reserves after the repayment are worked out from the terms and the repayment
+            // rather than read from the vaults. Anyone may contribute to the pool directly while the
+            // flash swap is outstanding, and such a contribution must not count as repayment.
+            let reserves_before = [terms.reserves_before.0, terms.reserves_before.1];
+            let reserves_after_fee: Vec<Decimal> = self
+                .vault_reserves()
+                .into_keys()
+                .zip(reserves_before)
+                .map(|(resource_address, reserves_before)| {
+                    let mut reserves_after = reserves_before;
+                    if resource_address == terms.resource_address {
+                        reserves_after = reserves_after.checked_sub(terms.amount).unwrap();
+                    }
+                    // The repayment is the input which pays the fee.
+                    if resource_address == repayment_resource {
+                        reserves_after = reserves_after
+                            .checked_add(
+                                repayment_amount
+                                    .checked_mul(
+                                        Decimal::ONE.checked_sub(self.fee_fraction()).unwrap(),
+                                    )
+                                    .unwrap(),
+                            )
+                            .unwrap();
+                    }
+
+                    reserves_after
+                })
+                .collect();
+
+            assert!(
+                self.calculate_invariant(reserves_after_fee[0], reserves_after_fee[1])
+                    >= self.calculate_invariant(reserves_before[0], reserves_before[1]),
+                "Insufficient repayment given for your flash swap!"
+            );
+
+            // We have our payment; we can now burn the transient token
+            flash_swap_receipt.burn();
+            self.flash_swap_in_progress = false;
+            self.assert_price_move_within_limit();
+        }
+
+        pub fn get_curve(&self) -> Curve {
+            self.curve
+        }
+
+        /// Returns the fee charged on swaps in basis points.
+        pub fn get_fee(&self) -> u16 {
+            self.fee_bps
+        }
+
+        /// Changes the fee charged on swaps. Only the owner may call this, and the new fee may not
+        /// exceed the hard cap of the blueprint.
+        pub fn set_fee(&mut self, fee_bps: u16) {
+            assert!(
+                fee_bps <= MAX_FEE_BPS,
+                "Fee must not exceed {} basis points",
+                MAX_FEE_BPS
+            );
+            self.fee_bps = fee_bps;
+        }
+
+        /// Returns the share of the swap fee diverted to the protocol, in basis points of the fee.
+        pub fn get_protocol_fee_share(&self) -> u16 {
+            self.protocol_fee_share_bps
+        }
+
+        /// Changes the share of the swap fee diverted to the protocol, in basis points of the fee.
+        /// Only the owner may call this, and the share may not exceed the hard cap of the
+        /// blueprint. Fees paid on flash swaps are always left to the liquidity providers.
+        pub fn set_protocol_fee_share(&mut self, protocol_fee_share_bps: u16) {
+            assert!(
+                protocol_fee_share_bps <= MAX_PROTOCOL_FEE_SHARE_BPS,
+                "Protocol fee share must not exceed {} basis points",
+                MAX_PROTOCOL_FEE_SHARE_BPS
+            );
+            self.protocol_fee_share_bps = protocol_fee_share_bps;
+        }
+
+        /// Withdraws all the protocol fees collected so far, in the order of the pool's resources.
+        pub fn collect_protocol_fees(&mut self) -> (Bucket, Bucket) {
+            let mut protocol_fees = self
+                .vault_reserves()
+                .into_keys()
+                .map(|resource_address| {
+                    self.protocol_fees
+                        .get_mut(&resource_address)
+                        .unwrap()
+                        .take_all()
+                })
+                .collect::<Vec<Bucket>>()
+                .into_iter();
+
+            (protocol_fees.next().unwrap(), protocol_fees.next().unwrap())
+        }
+
+        /// Stops all swaps and the addition of liquidity, for example while one of the resources of
+        /// the pool is being exploited. Liquidity providers can still remove their liquidity.
+        pub fn pause(&mut self) {
+            self.paused = true;
+        }
+
+        pub fn unpause(&mut self) {
+            self.paused = false;
+        }
+
+        /// Returns the largest price move allowed within one epoch, in basis points, or `None` if
+        /// the circuit breaker is off.
+        pub fn get_max_price_move(&self) -> Option<u16> {
+            self.max_price_move_bps
+        }
+
+        /// Sets the largest price move that swaps may cause within one epoch, in basis points of
+        /// the price at the start of the epoch, or turns the circuit breaker off with `None`. A
+        /// move of zero would halt all swaps, pausing is meant for that.
+        pub fn set_max_price_move(&mut self, max_price_move_bps: Option<u16>) {
+            if let Some(max_price_move_bps) = max_price_move_bps {
+                assert!(
+                    max_price_move_bps > 0 && max_price_move_bps <= BASIS_POINTS,
+                    "Max price move must be between 1 and {} basis points",
+                    BASIS_POINTS
+                );
+            }
+            self.max_price_move_bps = max_price_move_bps;
+        }
+
+        /// Prices a swap of `input_amount` on the curve of the pool: the output is computed so that
+        /// the invariant is unchanged by the input net of the fee. Both swaps and quotes go through
+        /// this function so that a quote always matches what the swap would do.
+        fn calculate_output_amount(
+            &self,
+            input_amount: Decimal,
+            input_reserves: Decimal,
+            output_reserves: Decimal,
+        ) -> Decimal {
+            let input_amount_after_fee = input_amount
+                .checked_mul(Decimal::ONE.checked_sub(self.fee_fraction()).unwrap())
+                .unwrap();
+
+            match self.curve {
+                Curve::ConstantProduct => input_amount_after_fee
+                    .checked_mul(output_reserves)
+                    .unwrap()
+                    .checked_div(input_reserves.checked_add(input_amount_after_fee).unwrap())
+                    .unwrap(),
+                Curve::StableSwap { amplification } => {
+                    let d = stableswap::compute_d(input_reserves, output_reserves, amplification);
+                    let new_output_reserves = stableswap::compute_y(
+                        input_reserves.checked_add(input_amount_after_fee).unwrap(),
+                        d,
+                        amplification,
+                    );
+
+                    // Newton's method only gets within an atto of the exact reserves, so one atto
+                    // is taken off the output to make sure that rounding favors the pool.
+                    cmp::max(
+                        output_reserves
+                            .checked_sub(new_output_reserves)
+                            .unwrap()
+                            .checked_sub(dec!("0.000000000000000001"))
+                            .unwrap(),
+                        Decimal::ZERO,
+                    )
+                }
+            }
+        }
+
+        /// The inverse of `calculate_output_amount`: the input, fee included, that must be paid to
+        /// receive `output_amount`. The result is rounded up by one atto so that rounding always
+        /// favors the pool.
+        fn calculate_input_amount(
+            &self,
+            output_amount: Decimal,
+            input_reserves: Decimal,
+            output_reserves: Decimal,
+        ) -> Decimal {
+            assert!(
+                output_amount < output_reserves,
+                "Not enough liquidity in the pool for the desired output"
+            );
+
+            let input_amount_after_fee = match self.curve {
+                Curve::ConstantProduct => output_amount
+                    .checked_mul(input_reserves)
+                    .unwrap()
+                    .checked_div(output_reserves.checked_sub(output_amount).unwrap())
+                    .unwrap(),
+                Curve::StableSwap { amplification } => {
+                    let d = stableswap::compute_d(input_reserves, output_reserves, amplification);
+                    let new_input_reserves = stableswap::compute_y(
+                        output_reserves.checked_sub(output_amount).unwrap(),
+                        d,
+                        amplification,
+                    );
+
+                    new_input_reserves
+                        .checked_sub(input_reserves)
+                        .unwrap()
+                        .checked_add(dec!("0.000000000000000001"))
+                        .unwrap()
+                }
+            };
+
+            input_amount_after_fee
+                .checked_div(Decimal::ONE.checked_sub(self.fee_fraction()).unwrap())
+                .unwrap()
+                .checked_add(dec!("0.000000000000000001"))
+                .unwrap()
+        }
+
+        /// Finds the part of `input_amount` to swap so that what is left of the input and the
+        /// output of the swap are in the same ratio as the reserves of the pool after the swap,
+        /// which is what `contribute` needs to take both in full. The more is swapped, the less
+        /// input is left for ever more output, so the right amount is found by bisection.
+        fn calculate_zap_swap_amount(
+            &self,
+            input_amount: Decimal,
+            input_reserves: Decimal,
+            output_reserves: Decimal,
+        ) -> Decimal {
+            // The part of the input that stays in the pool, the rest being the protocol's fee.
+            let retained_fraction = Decimal::ONE
+                .checked_sub(self.protocol_fee_fraction())
+                .unwrap();
+
+            let mut low = Decimal::ZERO;
+            let mut high = input_amount;
+            for _ in 0..MAX_ZAP_ITERATIONS {
+                let middle = low
+                    .checked_add(high.checked_sub(low).unwrap().checked_div(2u64).unwrap())
+                    .unwrap();
+                if middle == low {
+                    break;
+                }
+
+                let output_amount =
+                    self.calculate_output_amount(middle, input_reserves, output_reserves);
+                let input_left = input_amount.checked_sub(middle).unwrap();
+                let input_reserves_after = input_reserves
+                    .checked_add(middle.checked_mul(retained_fraction).unwrap())
+                    .unwrap();
+                let output_reserves_after = output_reserves.checked_sub(output_amount).unwrap();
+
+                // Compares input_left / output_amount with the ratio of the reserves.
+                if input_left.checked_mul(output_reserves_after).unwrap()
+                    > output_amount.checked_mul(input_reserves_after).unwrap()
+                {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+
+            low
+        }
+
+        /// The marginal price of the resource held in `reserves` expressed in the resource held in
+        /// `other_reserves`, ignoring the fee.
+        fn calculate_spot_price(&self, reserves: Decimal, other_reserves: Decimal) -> Decimal {
+            match self.curve {
+                Curve::ConstantProduct => other_reserves.checked_div(reserves).unwrap(),
+                Curve::StableSwap { amplification } => {
+                    stableswap::spot_price(reserves, other_reserves, amplification)
+                }
+            }
+        }
+
+        /// The quantity that the curve of the pool keeps constant across swaps, which may only grow
+        /// through fees.
+        fn calculate_invariant(&self, reserves1: Decimal, reserves2: Decimal) -> Decimal {
+            match self.curve {
+                Curve::ConstantProduct => reserves1.checked_mul(reserves2).unwrap(),
+                Curve::StableSwap { amplification } => {
+                    stableswap::compute_d(reserves1, reserves2, amplification)
+                }
+            }
+        }
+
+        /// Moves the protocol's share of the fee charged on `input_bucket` out of the bucket and
+        /// into the protocol fee vault of its resource. Returns the part of the fee left to the
+        /// liquidity providers and the part taken by the protocol.
+        fn take_protocol_fee(&mut self, input_bucket: &mut Bucket) -> (Decimal, Decimal) {
+            let fee = input_bucket
+                .amount()
+                .checked_mul(self.fee_fraction())
+                .unwrap();
+            let protocol_fee = input_bucket
+                .amount()
+                .checked_mul(self.protocol_fee_fraction())
+                .unwrap();
+            if protocol_fee.is_zero() {
+                return (fee, Decimal::ZERO);
+            }
+
+            let protocol_fee_bucket = input_bucket.take_advanced(
+                protocol_fee,
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            );
+            let protocol_fee = protocol_fee_bucket.amount();
+            self.protocol_fees
+                .get_mut(&protocol_fee_bucket.resource_address())
+                .unwrap()
+                .put(protocol_fee_bucket);
+
+            (fee.checked_sub(protocol_fee).unwrap(), protocol_fee)
+        }
+
+        fn fee_fraction(&self) -> Decimal {
+            Decimal::from(self.fee_bps)
+                .checked_div(BASIS_POINTS)
+                .unwrap()
+        }
+
+        /// The part of every swap input taken by the protocol, as a fraction of the input.
+        fn protocol_fee_fraction(&self) -> Decimal {
+            self.fee_fraction()
+                .checked_mul(self.protocol_fee_share_bps)
+                .unwrap()
+                .checked_div(BASIS_POINTS)
+                .unwrap()
+        }
+
+        /// Returns the reserves of the given resource along with the address and reserves of the
+        /// other resource of the pool.
+        fn reserves_for(
+            &self,
+            resource_address: ResourceAddress,
+        ) -> (Decimal, ResourceAddress, Decimal) {
+            let mut reserves = self.vault_reserves();
+
+            let resource_reserves = reserves
+                .remove(&resource_address)
+                .expect("Resource does not belong to the pool");
+            let (other_resource_address, other_reserves) = reserves.into_iter().next().unwrap();
+
+            (resource_reserves, other_resource_address, other_reserves)
+        }
+
+        /// Accumulates the current prices into the cumulative prices for the epochs elapsed since
+        /// the last observation, and records a new observation. This must be called before the
+        /// reserves change so that the elapsed epochs are weighted by the prices they actually had.
+        fn update_price_observations(&mut self) {
+            let current_epoch = Runtime::current_epoch();
+            if self.price_observations.last().unwrap().epoch >= current_epoch {
+                return;
+            }
+
+            let observation = self.price_observation_at(current_epoch);
+            self.price_observations.push(observation);
+            if self.price_observations.len() > MAX_PRICE_OBSERVATIONS {
+                self.price_observations.remove(0);
+            }
+        }
+
+        /// Extrapolates the last observation to the given epoch using the current reserves, which
+        /// are the reserves the pool has had since the last observation. Epochs during which the
+        /// pool had no liquidity do not add to the cumulative prices.
+        fn price_observation_at(&self, epoch: Epoch) -> PriceObservation {
+            let last_observation = self.price_observations.last().unwrap().clone();
+            let elapsed_epochs = epoch.number() - last_observation.epoch.number();
+
+            let reserves: Vec<Decimal> = self.vault_reserves().into_values().collect();
+            if elapsed_epochs == 0 || reserves[0].is_zero() || reserves[1].is_zero() {
+                return PriceObservation {
+                    epoch,
+                    ..last_observation
+                };
+            }
+
+            let price1 = self.calculate_spot_price(reserves[0], reserves[1]);
+            let price2 = self.calculate_spot_price(reserves[1], reserves[0]);
+            PriceObservation {
+                epoch,
+                price_cumulative1: last_observation
+                    .price_cumulative1
+                    .checked_add(price1.checked_mul(elapsed_epochs).unwrap())
+                    .unwrap(),
+                price_cumulative2: last_observation
+                    .price_cumulative2
+                    .checked_add(price2.checked_mul(elapsed_epochs).unwrap())
+                    .unwrap(),
+            }
+        }
+
+        /// Returns the spot price of the first resource of the pool in terms of the second one, or
+        /// zero if the pool is empty.
+        fn current_price(&self) -> Decimal {
+            let reserves: Vec<Decimal> = self.vault_reserves().into_values().collect();
+            if reserves[0].is_zero() || reserves[1].is_zero() {
+                return Decimal::ZERO;
+            }
+
+            self.calculate_spot_price(reserves[0], reserves[1])
+        }
+
+        /// Records the current price as the price at the start of the epoch if this is the first
+        /// swap of the epoch, or if the pool had no price yet. This must be called before the
+        /// reserves are changed by the swap.
+        fn record_epoch_open_price(&mut self) {
+            let epoch = Runtime::current_epoch();
+            if self.epoch_open_price.0 != epoch || self.epoch_open_price.1.is_zero() {
+                self.epoch_open_price = (epoch, self.current_price());
+            }
+        }
+
+        /// Trips the circuit breaker, failing the transaction, if the price has moved further from
+        /// the price at the start of the epoch than the owner allows.
+        fn assert_price_move_within_limit(&self) {
+            let Some(max_price_move_bps) = self.max_price_move_bps else {
+                return;
+            };
+            let open_price = self.epoch_open_price.1;
+            if open_price.is_zero() {
+                return;
+            }
+
+            let price = self.current_price();
+            let price_move = cmp::max(price, open_price)
+                .checked_sub(cmp::min(price, open_price))
+                .unwrap()
+                .checked_div(open_price)
+                .unwrap();
+            assert!(
+                price_move
+                    <= Decimal::from(max_price_move_bps)
+                        .checked_div(BASIS_POINTS)
+                        .unwrap(),
+                "Circuit breaker tripped: the price moved by more than {} basis points this epoch",
+                max_price_move_bps
+            );
+        }
+
+        fn assert_not_paused(&self) {
+            assert!(!self.paused, "The pool is paused");
+        }
+
+        fn assert_no_flash_swap_in_progress(&self) {
+            assert!(
+                !self.flash_swap_in_progress,
+                "A flash swap must be repaid before the pool can be used"
+            );
+        }
+
+        fn assert_deadline_not_passed(deadline_epoch: Epoch) {
+            assert!(
+                Runtime::current_epoch() <= deadline_epoch,
+                "Deadline epoch {} has passed",
+                deadline_epoch.number()
+            );
         }
 
         fn vault_reserves(&self) -> IndexMap<ResourceAddress, Decimal> {
@@ -93,3 +1154,36 @@ mod radiswap {
         }
     }
 }
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct InstantiationEvent {
+    pub owner_role: OwnerRole,
+    pub resource_address1: ResourceAddress,
+    pub resource_address2: ResourceAddress,
+    pub component_address: ComponentAddress,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct AddLiquidityEvent {
+    /// The resources taken in by the pool, net of any change given back.
+    pub contributed_resources: [(ResourceAddress, Decimal); 2],
+    /// The amount of pool units minted for the contribution.
+    pub pool_units_amount: Decimal,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct RemoveLiquidityEvent {
+    pub pool_units_amount: Decimal,
+    pub redeemed_resources: [(ResourceAddress, Decimal); 2],
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct SwapEvent {
+    pub input: (ResourceAddress, Decimal),
+    pub output: (ResourceAddress, Decimal),
+    /// The part of the fee, in the input resource, which is left in the pool for the liquidity
+    /// providers.
+    pub lp_fee: Decimal,
+    /// The part of the fee, in the input resource, which is diverted to the protocol treasury.
+    pub protocol_fee: Decimal,
+}
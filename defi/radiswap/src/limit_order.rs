@@ -0,0 +1,302 @@
+use crate::radiswap::Radiswap;
+use crate::BASIS_POINTS;
+use scrypto::prelude::*;
+
+/// The highest keeper tip that an order book may pay out of the input of an order, 1%.
+const MAX_KEEPER_TIP_BPS: u16 = 100;
+
+/// The condition on the spot price of the input resource of a limit order under which the order
+/// may be filled.
+#[derive(ScryptoSbor, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderTrigger {
+    /// The order may be filled once the price is at or above the target price, which makes it a
+    /// take-profit order.
+    AtOrAbove,
+    /// The order may be filled once the price is at or below the target price, which makes it a
+    /// stop order.
+    AtOrBelow,
+}
+
+/// The terms of a limit order, held by its owner as an NFT.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct LimitOrder {
+    pub input_resource_address: ResourceAddress,
+    pub input_amount: Decimal,
+    pub output_resource_address: ResourceAddress,
+    /// The spot price of the input resource, in the output resource, at which the order fills.
+    pub target_price: Decimal,
+    pub trigger: OrderTrigger,
+    /// The lowest price, in the output resource, at which the input net of the keeper tip may be
+    /// sold when the order is filled.
+    pub min_execution_price: Decimal,
+}
+
+/// The resources escrowed by the order book for a single order. Before the order is filled the
+/// input vault holds the input, after it is filled the output vault holds the output.
+#[derive(ScryptoSbor)]
+pub struct EscrowedOrder {
+    input: Vault,
+    output: Vault,
+    target_price: Decimal,
+    trigger: OrderTrigger,
+    min_execution_price: Decimal,
+}
+
+/// Emitted when an order is placed, which is how keepers find the orders that they may fill.
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct OrderPlacedEvent {
+    pub order_id: NonFungibleLocalId,
+    pub input: (ResourceAddress, Decimal),
+    pub target_price: Decimal,
+    pub trigger: OrderTrigger,
+}
+
+#[blueprint]
+#[events(OrderPlacedEvent)]
+mod radiswap_limit_order_book {
+    /// A RadiswapLimitOrderBook escrows the inputs of limit orders against a single Radiswap pool.
+    /// Nobody has to watch the price on behalf of the traders: anyone may fill an order once the
+    /// spot price of the pool crosses its target, and is paid a keeper tip out of the input of
+    /// the order for doing so. Traders keep custody of their orders through the order NFT, which
+    /// is needed to cancel an order or to claim the output of a filled one.
+    struct RadiswapLimitOrderBook {
+        /// The pool that orders are filled against.
+        pool: Global<Radiswap>,
+        /// The share of the input of an order paid to whoever fills it, in basis points.
+        keeper_tip_bps: u16,
+        /// The resource manager of the order NFTs given out to traders.
+        order_manager: ResourceManager,
+        /// The escrowed resources of every order, keyed by the local id of the order NFT.
+        orders: KeyValueStore<NonFungibleLocalId, EscrowedOrder>,
+    }
+
+    impl RadiswapLimitOrderBook {
+        pub fn instantiate_order_book(
+            pool: Global<Radiswap>,
+            keeper_tip_bps: u16,
+        ) -> Global<RadiswapLimitOrderBook> {
+            assert!(
+                keeper_tip_bps <= MAX_KEEPER_TIP_BPS,
+                "Keeper tip must not exceed {} basis points",
+                MAX_KEEPER_TIP_BPS
+            );
+
+            let (address_reservation, component_address) =
+                Runtime::allocate_component_address(RadiswapLimitOrderBook::blueprint_id());
+
+            let order_manager =
+                ResourceBuilder::new_ruid_non_fungible::<LimitOrder>(OwnerRole::None)
+                    .metadata(metadata!(
+                        init {
+                            "name" => "Radiswap Limit Order".to_owned(), locked;
+                        }
+                    ))
+                    .mint_roles(mint_roles!(
+                        minter => rule!(require(global_caller(component_address)));
+                        minter_updater => rule!(deny_all);
+                    ))
+                    .burn_roles(burn_roles!(
+                        burner => rule!(require(global_caller(component_address)));
+                        burner_updater => rule!(deny_all);
+                    ))
+                    .create_with_no_initial_supply();
+
+            Self {
+                pool,
+                keeper_tip_bps,
+                order_manager,
+                orders: KeyValueStore::new(),
+            }
+            .instantiate()
+            .prepare_to_globalize(OwnerRole::None)
+            .with_address(address_reservation)
+            .globalize()
+        }
+
+        /// Escrows the input bucket in an order which may be filled once the spot price of the
+        /// input resource is at or above `target_price`. The output of the order is guaranteed to
+        /// be at least the input net of the keeper tip times the target price.
+        pub fn place_take_profit_order(&mut self, input: Bucket, target_price: Decimal) -> Bucket {
+            self.place_order(input, target_price, OrderTrigger::AtOrAbove, target_price)
+        }
+
+        /// Escrows the input bucket in an order which may be filled once the spot price of the
+        /// input resource is at or below `target_price`. A stop order is meant to get out of a
+        /// falling market, so it may fill below the target price, but never below the target price
+        /// less `max_slippage_bps`. Without that floor anyone could push the spot price down within
+        /// a transaction, fill the order for next to nothing and swap the price back up.
+        pub fn place_stop_order(
+            &mut self,
+            input: Bucket,
+            target_price: Decimal,
+            max_slippage_bps: u16,
+        ) -> Bucket {
+            assert!(
+                max_slippage_bps <= BASIS_POINTS,
+                "Maximum slippage must not exceed {} basis points",
+                BASIS_POINTS
+            );
+            let min_execution_price = target_price
+                .checked_mul(BASIS_POINTS - max_slippage_bps)
+                .unwrap()
+                .checked_div(BASIS_POINTS)
+                .unwrap();
+
+            self.place_order(
+                input,
+                target_price,
+                OrderTrigger::AtOrBelow,
+                min_execution_price,
+            )
+        }
+
+        /// Fills the given order by swapping its input through the pool, provided that the spot
+        /// price of the pool has crossed the target price of the order. The output is kept for
+        /// the owner of the order to claim, and the keeper tip is returned to the caller.
+        pub fn fill(&mut self, order_id: NonFungibleLocalId) -> Bucket {
+            let mut order = self.orders.get_mut(&order_id).expect("No such order");
+            assert!(
+                !order.input.is_empty(),
+                "The order has already been filled or cancelled"
+            );
+
+            let input_resource_address = order.input.resource_address();
+            let price = self.pool.spot_price(input_resource_address);
+            match order.trigger {
+                OrderTrigger::AtOrAbove => assert!(
+                    price >= order.target_price,
+                    "Price of {} has not reached the target price of {}",
+                    price,
+                    order.target_price
+                ),
+                OrderTrigger::AtOrBelow => assert!(
+                    price <= order.target_price,
+                    "Price of {} has not fallen to the target price of {}",
+                    price,
+                    order.target_price
+                ),
+            }
+
+            let mut input = order.input.take_all();
+            let keeper_tip = input.take_advanced(
+                input
+                    .amount()
+                    .checked_mul(self.keeper_tip_bps)
+                    .unwrap()
+                    .checked_div(BASIS_POINTS)
+                    .unwrap(),
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            );
+
+            let min_output = input
+                .amount()
+                .checked_mul(order.min_execution_price)
+                .unwrap();
+            let output = self.pool.swap(input);
+            assert!(
+                output.amount() >= min_output,
+                "Output amount of {} is below the minimum output of {}",
+                output.amount(),
+                min_output
+            );
+            order.output.put(output);
+
+            keeper_tip
+        }
+
+        /// Cancels an order which has not been filled yet, burning the order NFT and returning the
+        /// escrowed input.
+        pub fn cancel(&mut self, order: Bucket) -> Bucket {
+            let order_id = self.check_and_burn_order(order);
+            let mut order = self.orders.get_mut(&order_id).unwrap();
+            assert!(
+                !order.input.is_empty(),
+                "The order has already been filled, its output must be claimed instead"
+            );
+
+            order.input.take_all()
+        }
+
+        /// Claims the output of a filled order, burning the order NFT.
+        pub fn claim(&mut self, order: Bucket) -> Bucket {
+            let order_id = self.check_and_burn_order(order);
+            let mut order = self.orders.get_mut(&order_id).unwrap();
+            assert!(
+                order.input.is_empty(),
+                "The order has not been filled yet, it must be cancelled instead"
+            );
+
+            order.output.take_all()
+        }
+
+        fn place_order(
+            &mut self,
+            input: Bucket,
+            target_price: Decimal,
+            trigger: OrderTrigger,
+            min_execution_price: Decimal,
+        ) -> Bucket {
+            assert!(!input.is_empty(), "The input of an order must not be empty");
+            assert!(
+                target_price > Decimal::ZERO,
+                "Target price must be positive"
+            );
+
+            let input_resource_address = input.resource_address();
+            let (resource_address1, resource_address2) = self.pool.get_resource_addresses();
+            let output_resource_address = if input_resource_address == resource_address1 {
+                resource_address2
+            } else if input_resource_address == resource_address2 {
+                resource_address1
+            } else {
+                panic!("Resource does not belong to the pool")
+            };
+
+            let order = self.order_manager.mint_ruid_non_fungible(LimitOrder {
+                input_resource_address,
+                input_amount: input.amount(),
+                output_resource_address,
+                target_price,
+                trigger,
+                min_execution_price,
+            });
+
+            let order_id = order.as_non_fungible().non_fungible_local_id();
+            Runtime::emit_event(OrderPlacedEvent {
+                order_id: order_id.clone(),
+                input: (input_resource_address, input.amount()),
+                target_price,
+                trigger,
+            });
+
+            let mut input_vault = Vault::new(input_resource_address);
+            input_vault.put(input);
+            self.orders.insert(
+                order_id,
+                EscrowedOrder {
+                    input: input_vault,
+                    output: Vault::new(output_resource_address),
+                    target_price,
+                    trigger,
+                    min_execution_price,
+                },
+            );
+
+            order
+        }
+
+        /// Checks that the bucket holds a single order NFT of this order book, burns it and returns
+        /// its local id.
+        fn check_and_burn_order(&self, order: Bucket) -> NonFungibleLocalId {
+            assert_eq!(
+                order.resource_address(),
+                self.order_manager.address(),
+                "Incorrect resource passed in for the order"
+            );
+            let order_id = order.as_non_fungible().non_fungible_local_id();
+            order.burn();
+
+            order_id
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::radiswap::Radiswap;
+use crate::sorted_pair;
+use scrypto::prelude::*;
+
+#[blueprint]
+mod radiswap_router {
+    enable_method_auth! {
+        methods {
+            register_pool => restrict_to: [OWNER];
+            deregister_pool => restrict_to: [OWNER];
+            get_pool => PUBLIC;
+            get_amount_out_along_path => PUBLIC;
+            swap_along_path => PUBLIC;
+        }
+    }
+
+    /// A RadiswapRouter keeps a registry of Radiswap pools and swaps along a path of resources by
+    /// going through the pool of each consecutive pair of the path. This allows trading between
+    /// two resources that do not share a pool but are both paired against a common resource.
+    struct RadiswapRouter {
+        /// The registered pools, keyed by the sorted pair of the resources that they trade.
+        pools: HashMap<(ResourceAddress, ResourceAddress), Global<Radiswap>>,
+    }
+
+    impl RadiswapRouter {
+        pub fn instantiate_router(owner_role: OwnerRole) -> Global<RadiswapRouter> {
+            Self {
+                pools: HashMap::new(),
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role)
+            .globalize()
+        }
+
+        /// Registers a pool under the pair of resources that it trades, replacing any pool that
+        /// was previously registered for the same pair.
+        pub fn register_pool(&mut self, pool: Global<Radiswap>) {
+            let (resource_address1, resource_address2) = pool.get_resource_addresses();
+            self.pools
+                .insert(sorted_pair(resource_address1, resource_address2), pool);
+        }
+
+        pub fn deregister_pool(
+            &mut self,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+        ) {
+            self.pools
+                .remove(&sorted_pair(resource_address1, resource_address2))
+                .expect("No pool is registered for this pair");
+        }
+
+        pub fn get_pool(
+            &self,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+        ) -> Option<Global<Radiswap>> {
+            self.pools
+                .get(&sorted_pair(resource_address1, resource_address2))
+                .cloned()
+        }
+
+        /// Returns the output that `swap_along_path` would currently give for `input_amount` of
+        /// the first resource of the path.
+        pub fn get_amount_out_along_path(
+            &self,
+            input_amount: Decimal,
+            path: Vec<ResourceAddress>,
+        ) -> Decimal {
+            Self::assert_valid_path(&path);
+
+            let mut amount = input_amount;
+            for hop in path.windows(2) {
+                amount = self.pool_for(hop[0], hop[1]).get_amount_out(hop[0], amount);
+            }
+            amount
+        }
+
+        /// Swaps the input bucket along the given path of resources. The path starts with the
+        /// resource of the input bucket and ends with the resource to receive, and every
+        /// consecutive pair of the path must have a registered pool. Fails if the final output is
+        /// less than `min_output`.
+        pub fn swap_along_path(
+            &mut self,
+            input_bucket: Bucket,
+            path: Vec<ResourceAddress>,
+            min_output: Decimal,
+        ) -> Bucket {
+            Self::assert_valid_path(&path);
+            assert_eq!(
+                input_bucket.resource_address(),
+                path[0],
+                "The path must start with the resource of the input bucket"
+            );
+
+            let mut bucket = input_bucket;
+            for hop in path.windows(2) {
+                bucket = self.pool_for(hop[0], hop[1]).swap(bucket);
+            }
+
+            assert!(
+                bucket.amount() >= min_output,
+                "Output amount of {} is below the minimum output of {}",
+                bucket.amount(),
+                min_output
+            );
+
+            bucket
+        }
+
+        fn pool_for(
+            &self,
+            resource_address1: ResourceAddress,
+            resource_address2: ResourceAddress,
+        ) -> Global<Radiswap> {
+            self.get_pool(resource_address1, resource_address2)
+                .expect("No pool is registered for a pair of the path")
+        }
+
+        fn assert_valid_path(path: &[ResourceAddress]) {
+            assert!(
+                path.len() >= 2,
+                "A path must contain at least two resources"
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! The StableSwap invariant from the Curve whitepaper, specialised to pools of two resources:
+//!
+//! `A * n^n * (x + y) + D = A * D * n^n + D^(n + 1) / (n^n * x * y)` with `n = 2`
+//!
+//! The amplification coefficient `A` controls how flat the curve is around the point where both
+//! reserves are equal. The larger it is, the closer the pool behaves to a constant-sum pool near
+//! the peg, while still behaving like a constant-product pool far from it.
+
+use scrypto::prelude::*;
+
+/// The number of resources in the pool, `n` in the invariant.
+const N_COINS: u64 = 2;
+
+/// Newton's method converges in a handful of iterations for sensible reserves, this is only a
+/// safety net against inputs for which it does not.
+const MAX_ITERATIONS: usize = 255;
+
+/// Computes `D`, the invariant of a pool holding `reserves1` and `reserves2`, which is the total
+/// amount of resources the pool would hold if both reserves were equal.
+pub fn compute_d(reserves1: Decimal, reserves2: Decimal, amplification: Decimal) -> Decimal {
+    let sum = reserves1.checked_add(reserves2).unwrap();
+    if sum.is_zero() {
+        return Decimal::ZERO;
+    }
+
+    let ann = amplification.checked_mul(N_COINS * N_COINS).unwrap();
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D^(n + 1) / (n^n * x * y), computed one factor at a time to keep intermediate values
+        // small.
+        let d_product = d
+            .checked_mul(d)
+            .unwrap()
+            .checked_div(reserves1.checked_mul(N_COINS).unwrap())
+            .unwrap()
+            .checked_mul(d)
+            .unwrap()
+            .checked_div(reserves2.checked_mul(N_COINS).unwrap())
+            .unwrap();
+
+        let previous_d = d;
+        let numerator = ann
+            .checked_mul(sum)
+            .unwrap()
+            .checked_add(d_product.checked_mul(N_COINS).unwrap())
+            .unwrap()
+            .checked_mul(d)
+            .unwrap();
+        let denominator = ann
+            .checked_sub(Decimal::ONE)
+            .unwrap()
+            .checked_mul(d)
+            .unwrap()
+            .checked_add(d_product.checked_mul(N_COINS + 1).unwrap())
+            .unwrap();
+        d = numerator.checked_div(denominator).unwrap();
+
+        if has_converged(d, previous_d) {
+            return d;
+        }
+    }
+
+    panic!("StableSwap invariant did not converge")
+}
+
+/// Computes the reserves of one resource which keep the invariant at `d` when the reserves of the
+/// other resource are `other_reserves`.
+pub fn compute_y(other_reserves: Decimal, d: Decimal, amplification: Decimal) -> Decimal {
+    let ann = amplification.checked_mul(N_COINS * N_COINS).unwrap();
+
+    let c = d
+        .checked_mul(d)
+        .unwrap()
+        .checked_div(other_reserves.checked_mul(N_COINS).unwrap())
+        .unwrap()
+        .checked_mul(d)
+        .unwrap()
+        .checked_div(ann.checked_mul(N_COINS).unwrap())
+        .unwrap();
+    let b = other_reserves
+        .checked_add(d.checked_div(ann).unwrap())
+        .unwrap();
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let previous_y = y;
+        y = y
+            .checked_mul(y)
+            .unwrap()
+            .checked_add(c)
+            .unwrap()
+            .checked_div(
+                y.checked_mul(2)
+                    .unwrap()
+                    .checked_add(b)
+                    .unwrap()
+                    .checked_sub(d)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        if has_converged(y, previous_y) {
+            return y;
+        }
+    }
+
+    panic!("StableSwap invariant did not converge")
+}
+
+/// Computes the marginal price of the resource held in `reserves`, expressed in the resource held
+/// in `other_reserves`. This is the negated slope of the invariant curve at the current reserves.
+pub fn spot_price(reserves: Decimal, other_reserves: Decimal, amplification: Decimal) -> Decimal {
+    let ann = amplification.checked_mul(N_COINS * N_COINS).unwrap();
+    let d = compute_d(reserves, other_reserves, amplification);
+
+    // D^(n + 1) / (n^n * x * y), the partial derivatives of the invariant with respect to each
+    // reserve are `ann + d_product / reserve`.
+    let d_product = d
+        .checked_mul(d)
+        .unwrap()
+        .checked_div(reserves.checked_mul(N_COINS).unwrap())
+        .unwrap()
+        .checked_mul(d)
+        .unwrap()
+        .checked_div(other_reserves.checked_mul(N_COINS).unwrap())
+        .unwrap();
+
+    ann.checked_add(d_product.checked_div(reserves).unwrap())
+        .unwrap()
+        .checked_div(
+            ann.checked_add(d_product.checked_div(other_reserves).unwrap())
+                .unwrap(),
+        )
+        .unwrap()
+}
+
+/// Decimal divisions truncate, so Newton's method may end up oscillating by a single atto around
+/// the exact solution rather than reaching it.
+fn has_converged(value: Decimal, previous_value: Decimal) -> bool {
+    let difference = if value > previous_value {
+        value.checked_sub(previous_value).unwrap()
+    } else {
+        previous_value.checked_sub(value).unwrap()
+    };
+    difference <= dec!("0.000000000000000001")
+}
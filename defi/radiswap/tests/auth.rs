@@ -0,0 +1,205 @@
+use radix_engine::errors::{ApplicationError, RuntimeError, SystemModuleError};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn only_the_owner_may_manage_fees() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the owner and the account of someone else
+    let (owner_public_key, _private_key, owner_account) = test_runner.new_allocated_account();
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let radiswap = instantiate_owned_radiswap(
+        &mut test_runner,
+        &owner_public_key,
+        rule!(deny_all),
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `set_fee`, `set_protocol_fee_share` and `collect_protocol_fees` methods, which
+    // must fail for anyone but the owner.
+    for (method_name, args) in [
+        ("set_fee", manifest_args!(100u16)),
+        ("set_protocol_fee_share", manifest_args!(1000u16)),
+        ("collect_protocol_fees", manifest_args!()),
+    ] {
+        let manifest = ManifestBuilder::new()
+            .call_method(radiswap, method_name, args.clone())
+            .deposit_batch(account_component)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        receipt.expect_specific_failure(|error| {
+            matches!(
+                error,
+                RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+            )
+        });
+
+        let manifest = ManifestBuilder::new()
+            .call_method(radiswap, method_name, args)
+            .deposit_batch(owner_account)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&owner_public_key)],
+        );
+        receipt.expect_commit_success();
+    }
+}
+
+#[test]
+fn only_the_pauser_or_the_owner_may_pause() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the accounts of the owner, of the pauser and of someone else
+    let (owner_public_key, _private_key, owner_account) = test_runner.new_allocated_account();
+    let (pauser_public_key, _private_key, _pauser_account) = test_runner.new_allocated_account();
+    let (public_key, _private_key, _account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let radiswap = instantiate_owned_radiswap(
+        &mut test_runner,
+        &owner_public_key,
+        rule!(require(NonFungibleGlobalId::from_public_key(
+            &pauser_public_key
+        ))),
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `pause` and `unpause` methods, which must fail for anyone but the pauser and the
+    // owner, and the `set_max_price_move` method, which must fail for the pauser as well.
+    for (method_name, args, signer, succeeds) in [
+        ("pause", manifest_args!(), &public_key, false),
+        ("pause", manifest_args!(), &pauser_public_key, true),
+        ("unpause", manifest_args!(), &public_key, false),
+        ("unpause", manifest_args!(), &pauser_public_key, true),
+        ("pause", manifest_args!(), &owner_public_key, true),
+        ("unpause", manifest_args!(), &owner_public_key, true),
+        (
+            "set_max_price_move",
+            manifest_args!(Some(500u16)),
+            &pauser_public_key,
+            false,
+        ),
+        (
+            "set_max_price_move",
+            manifest_args!(Some(500u16)),
+            &owner_public_key,
+            true,
+        ),
+    ] {
+        let manifest = ManifestBuilder::new()
+            .call_method(radiswap, method_name, args)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(signer)],
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_specific_failure(|error| {
+                matches!(
+                    error,
+                    RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+                )
+            });
+        }
+    }
+}
+
+#[test]
+fn max_price_move_must_be_within_bounds() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let radiswap = instantiate_owned_radiswap(
+        &mut test_runner,
+        &public_key,
+        rule!(deny_all),
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `set_max_price_move` method with a move of zero, above 100%, at 100% and with the
+    // circuit breaker turned off.
+    for (max_price_move_bps, succeeds) in [
+        (Some(0u16), false),
+        (Some(10_001u16), false),
+        (Some(10_000u16), true),
+        (None, true),
+    ] {
+        let manifest = ManifestBuilder::new()
+            .call_method(
+                radiswap,
+                "set_max_price_move",
+                manifest_args!(max_price_move_bps),
+            )
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_specific_failure(|error| {
+                matches!(
+                    error,
+                    RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                        if message.contains("Max price move must be between")
+                )
+            });
+        }
+    }
+}
+
+/// Instantiates a Radiswap pool for the given resources, owned by the given public key and
+/// pausable by whoever satisfies the given rule.
+fn instantiate_owned_radiswap(
+    test_runner: &mut DefaultTestRunner,
+    owner_public_key: &Secp256k1PublicKey,
+    pauser_rule: AccessRule,
+    resource_address1: ResourceAddress,
+    resource_address2: ResourceAddress,
+) -> ComponentAddress {
+    let owner_badge = NonFungibleGlobalId::from_public_key(owner_public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "Radiswap",
+            "new",
+            manifest_args!(
+                OwnerRole::Fixed(rule!(require(owner_badge.clone()))),
+                pauser_rule,
+                resource_address1,
+                resource_address2,
+                30u16
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+
+    receipt.expect_commit_success().new_component_addresses()[0]
+}
@@ -0,0 +1,74 @@
+//! Helpers shared by the test files of this package. Each test file only uses some of them, hence
+//! the `dead_code` allowance.
+#![allow(dead_code)]
+
+use radix_engine::transaction::TransactionReceipt;
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+/// Instantiates a Radiswap pool for the given resources with the given fee and adds 100 of each as
+/// liquidity.
+pub fn instantiate_funded_radiswap(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    package_address: PackageAddress,
+    resource_address1: ResourceAddress,
+    resource_address2: ResourceAddress,
+    fee_bps: u16,
+) -> ComponentAddress {
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "Radiswap",
+            "new",
+            manifest_args!(
+                OwnerRole::None,
+                rule!(deny_all),
+                resource_address1,
+                resource_address2,
+                fee_bps
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let radiswap = receipt.expect_commit_success().new_component_addresses()[0];
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_address1, dec!(100))
+        .withdraw_from_account(account_component, resource_address2, dec!(100))
+        .take_all_from_worktop(resource_address1, "resource1")
+        .take_all_from_worktop(resource_address2, "resource2")
+        .call_method_with_name_lookup(radiswap, "add_liquidity", |lookup| {
+            (lookup.bucket("resource1"), lookup.bucket("resource2"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    receipt.expect_commit_success();
+
+    radiswap
+}
+
+/// Decodes the event of the given name emitted by the transaction, failing if there is none.
+pub fn find_event<T: ScryptoDecode>(
+    test_runner: &DefaultTestRunner,
+    receipt: &TransactionReceipt,
+    name: &str,
+) -> T {
+    let (_, data) = receipt
+        .expect_commit_success()
+        .application_events
+        .iter()
+        .find(|(event_type_identifier, _)| test_runner.event_name(event_type_identifier) == name)
+        .expect("Event was not emitted");
+
+    scrypto_decode(data).unwrap()
+}
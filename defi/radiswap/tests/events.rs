@@ -0,0 +1,174 @@
+use radiswap::{AddLiquidityEvent, InstantiationEvent, RemoveLiquidityEvent, SwapEvent};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+mod common;
+use common::{find_event, instantiate_funded_radiswap};
+
+#[test]
+fn liquidity_events_report_contributed_and_redeemed_amounts() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `new` function.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "Radiswap",
+            "new",
+            manifest_args!(
+                OwnerRole::None,
+                rule!(deny_all),
+                resource_a,
+                resource_b,
+                30u16
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    let commit = receipt.expect_commit_success();
+    let radiswap = commit.new_component_addresses()[0];
+    // The pool units are the first resource created, by the pool that Radiswap instantiates.
+    let pool_units = commit.new_resource_addresses()[0];
+
+    let event: InstantiationEvent = find_event(&test_runner, &receipt, "InstantiationEvent");
+    assert_eq!(event.component_address, radiswap);
+    assert_eq!(event.resource_address1, resource_a);
+    assert_eq!(event.resource_address2, resource_b);
+
+    // Test the `add_liquidity` method. The first contribution sets the ratio of the pool, so only
+    // 10 of the 20 B of the second one are needed to match its 10 A.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(100))
+        .withdraw_from_account(account_component, resource_b, dec!(100))
+        .take_all_from_worktop(resource_a, "resource_a")
+        .take_all_from_worktop(resource_b, "resource_b")
+        .call_method_with_name_lookup(radiswap, "add_liquidity", |lookup| {
+            (lookup.bucket("resource_a"), lookup.bucket("resource_b"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .withdraw_from_account(account_component, resource_b, dec!(20))
+        .take_all_from_worktop(resource_a, "resource_a")
+        .take_all_from_worktop(resource_b, "resource_b")
+        .call_method_with_name_lookup(radiswap, "add_liquidity", |lookup| {
+            (lookup.bucket("resource_a"), lookup.bucket("resource_b"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    let event: AddLiquidityEvent = find_event(&test_runner, &receipt, "AddLiquidityEvent");
+    assert_eq!(
+        event.contributed_resources,
+        [(resource_a, dec!(10)), (resource_b, dec!(10))]
+    );
+    let pool_units_amount = event.pool_units_amount;
+    assert!(pool_units_amount > Decimal::ZERO);
+
+    // Test the `remove_liquidity` method with the pool units minted by the second contribution.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, pool_units, pool_units_amount)
+        .take_all_from_worktop(pool_units, "pool_units")
+        .call_method_with_name_lookup(radiswap, "remove_liquidity", |lookup| {
+            (lookup.bucket("pool_units"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    let event: RemoveLiquidityEvent = find_event(&test_runner, &receipt, "RemoveLiquidityEvent");
+    assert_eq!(event.pool_units_amount, pool_units_amount);
+    let [(redeemed_resource_a, redeemed_a), (redeemed_resource_b, redeemed_b)] =
+        event.redeemed_resources;
+    assert_eq!(redeemed_resource_a, resource_a);
+    assert_eq!(redeemed_resource_b, resource_b);
+    // The account got back what was redeemed, which is the 10 of each it last contributed.
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(890).checked_add(redeemed_a).unwrap())
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        Some(dec!(890).checked_add(redeemed_b).unwrap())
+    );
+    assert!(redeemed_a > dec!("9.99") && redeemed_a <= dec!(10));
+}
+
+#[test]
+fn swap_event_reports_the_fee_portions() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    let radiswap = instantiate_funded_radiswap(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+        30,
+    );
+
+    // Test the `swap` method.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(radiswap, "swap", |lookup| (lookup.bucket("input"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    let event: SwapEvent = find_event(&test_runner, &receipt, "SwapEvent");
+    assert_eq!(event.input, (resource_a, dec!(10)));
+    assert_eq!(event.output.0, resource_b);
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        Some(dec!(900).checked_add(event.output.1).unwrap())
+    );
+    // The protocol fee is off by default, so the whole 0.3% fee is left to the liquidity providers.
+    assert_eq!(event.lp_fee, dec!("0.03"));
+    assert_eq!(event.protocol_fee, dec!(0));
+}
@@ -0,0 +1,135 @@
+use radix_engine::errors::{RuntimeError, SystemModuleError};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn factory_refuses_duplicate_pairs_in_either_order() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let factory = instantiate_factory(&mut test_runner, &public_key);
+
+    // Test the `create_pool` method.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            factory,
+            "create_pool",
+            manifest_args!(resource_a, resource_b, 30u16),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // Creating the same pair again, with the resources swapped, must fail.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            factory,
+            "create_pool",
+            manifest_args!(resource_b, resource_a, 30u16),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+
+    // Test the `get_pool` and `all_pools` methods.
+    let manifest = ManifestBuilder::new()
+        .call_method(factory, "get_pool", manifest_args!(resource_b, resource_a))
+        .call_method(factory, "all_pools", manifest_args!(0u64, 10u64))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    let commit_success = receipt.expect_commit_success();
+    let pool: Option<ComponentAddress> = commit_success.output(0);
+    let all_pools: Vec<ComponentAddress> = commit_success.output(1);
+    assert!(pool.is_some());
+    assert_eq!(all_pools, vec![pool.unwrap()]);
+}
+
+#[test]
+fn pools_are_owned_by_the_factory_owner() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the factory owner and the account of someone else
+    let (owner_public_key, _private_key, owner_account) = test_runner.new_allocated_account();
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, owner_account);
+    let factory = instantiate_factory(&mut test_runner, &owner_public_key);
+
+    // Anyone may create a pool.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            factory,
+            "create_pool",
+            manifest_args!(resource_a, resource_b, 30u16),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    let pool = receipt.expect_commit_success().new_component_addresses()[0];
+
+    // The creator of the pool may not change its fee, only the owner of the factory may.
+    let set_fee = ManifestBuilder::new()
+        .call_method(pool, "set_fee", manifest_args!(100u16))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        set_fee.clone(),
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+        )
+    });
+
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        set_fee,
+        vec![NonFungibleGlobalId::from_public_key(&owner_public_key)],
+    );
+    receipt.expect_commit_success();
+}
+
+/// Instantiates a RadiswapFactory owned by the given public key.
+fn instantiate_factory(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+) -> ComponentAddress {
+    let badge = NonFungibleGlobalId::from_public_key(public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Test the `instantiate_factory` function.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapFactory",
+            "instantiate_factory",
+            manifest_args!(OwnerRole::Fixed(rule!(require(badge.clone())))),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+
+    receipt.expect_commit_success().new_component_addresses()[0]
+}
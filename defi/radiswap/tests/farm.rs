@@ -0,0 +1,254 @@
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn stakers_keep_rewards_accrued_before_a_rate_change() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let owner_badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Any fungible resource can stand in for the pool units of a Radiswap pool.
+    let pool_units = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let reward = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    test_runner.set_current_epoch(Epoch::of(10));
+    let (farm, stake_position) = instantiate_funded_farm(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        pool_units,
+        reward,
+        dec!(1000),
+    );
+    stake(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        farm,
+        pool_units,
+    );
+
+    // Test the `claim_rewards` method after 10 epochs at a rate of 10 per epoch.
+    test_runner.set_current_epoch(Epoch::of(20));
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, stake_position, dec!(1))
+        .pop_from_auth_zone("stake_position")
+        .call_method_with_name_lookup(farm, "claim_rewards", |lookup| {
+            (lookup.proof("stake_position"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, reward),
+        Some(dec!(100))
+    );
+
+    // Test the `set_reward_rate` method, rewards accrued so far at the old rate are kept.
+    test_runner.set_current_epoch(Epoch::of(25));
+    let manifest = ManifestBuilder::new()
+        .call_method(farm, "set_reward_rate", manifest_args!(dec!(20)))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `unstake` method after 5 more epochs at the new rate.
+    test_runner.set_current_epoch(Epoch::of(30));
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, stake_position, dec!(1))
+        .take_all_from_worktop(stake_position, "stake_position")
+        .call_method_with_name_lookup(farm, "unstake", |lookup| (lookup.bucket("stake_position"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+    receipt.expect_commit_success();
+
+    // 100 claimed, then 5 epochs at 10 and 5 epochs at 20.
+    assert_eq!(
+        test_runner.account_balance(account_component, reward),
+        Some(dec!(250))
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, pool_units),
+        Some(dec!(1000))
+    );
+}
+
+#[test]
+fn rewards_are_capped_by_the_deposited_rewards() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let owner_badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let pool_units = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let reward = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    test_runner.set_current_epoch(Epoch::of(10));
+    let (farm, stake_position) = instantiate_funded_farm(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        pool_units,
+        reward,
+        dec!(50),
+    );
+    stake(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        farm,
+        pool_units,
+    );
+
+    // Test the `unstake` method after 10 epochs, which would be owed 100 if the farm had them.
+    test_runner.set_current_epoch(Epoch::of(20));
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, stake_position, dec!(1))
+        .take_all_from_worktop(stake_position, "stake_position")
+        .call_method_with_name_lookup(farm, "unstake", |lookup| (lookup.bucket("stake_position"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+    receipt.expect_commit_success();
+
+    assert_eq!(
+        test_runner.account_balance(account_component, reward),
+        Some(dec!(1000))
+    );
+}
+
+#[test]
+fn rewards_are_rounded_down_to_the_divisibility_of_the_reward_resource() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let owner_badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let pool_units = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let reward = test_runner.create_fungible_resource(dec!(1000), 6u8, account_component);
+
+    test_runner.set_current_epoch(Epoch::of(10));
+    let (farm, stake_position) = instantiate_funded_farm(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        pool_units,
+        reward,
+        dec!(1000),
+    );
+    // Three equal stakes, so that each is owed a third of the rewards.
+    for _ in 0..3 {
+        stake(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            farm,
+            pool_units,
+        );
+    }
+
+    // Test the `unstake` method after a single epoch, which owes more decimal places of the
+    // reward resource than it has.
+    test_runner.set_current_epoch(Epoch::of(11));
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, stake_position, dec!(1))
+        .take_all_from_worktop(stake_position, "stake_position")
+        .call_method_with_name_lookup(farm, "unstake", |lookup| (lookup.bucket("stake_position"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+    receipt.expect_commit_success();
+
+    assert_eq!(
+        test_runner.account_balance(account_component, reward),
+        Some(dec!("3.333333"))
+    );
+}
+
+/// Instantiates a farm paying out 10 rewards per epoch, funds it with the given amount of rewards
+/// and returns it along with the resource address of its stake positions.
+fn instantiate_funded_farm(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    package_address: PackageAddress,
+    pool_units: ResourceAddress,
+    reward: ResourceAddress,
+    funded_rewards: Decimal,
+) -> (ComponentAddress, ResourceAddress) {
+    let owner_badge = NonFungibleGlobalId::from_public_key(public_key);
+
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapFarm",
+            "instantiate_farm",
+            manifest_args!(
+                OwnerRole::Fixed(rule!(require(owner_badge.clone()))),
+                pool_units,
+                reward,
+                dec!(10)
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    let commit = receipt.expect_commit_success();
+    let farm = commit.new_component_addresses()[0];
+    let stake_position = commit.new_resource_addresses()[0];
+
+    let manifest =
+        ManifestBuilder::new()
+            .withdraw_from_account(account_component, reward, funded_rewards)
+            .take_all_from_worktop(reward, "rewards")
+            .call_method_with_name_lookup(farm, "deposit_rewards", |lookup| {
+                (lookup.bucket("rewards"),)
+            })
+            .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+    receipt.expect_commit_success();
+
+    (farm, stake_position)
+}
+
+/// Stakes 100 pool units in the farm and deposits the stake position in the account.
+fn stake(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    farm: ComponentAddress,
+    pool_units: ResourceAddress,
+) {
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, pool_units, dec!(100))
+        .take_all_from_worktop(pool_units, "pool_units")
+        .call_method_with_name_lookup(farm, "stake", |lookup| (lookup.bucket("pool_units"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    receipt.expect_commit_success();
+}
@@ -20,8 +20,10 @@ fn simple_radiswap_test() -> Result<(), RuntimeError> {
 
     let mut radiswap = Radiswap::new(
         OwnerRole::None,
+        rule!(deny_all),
         resource_address1,
         resource_address2,
+        30,
         package_address,
         &mut env,
     )?;
@@ -52,8 +54,10 @@ fn reading_and_asserting_against_radiswap_pool_state() -> Result<(), RuntimeErro
 
     let mut radiswap = Radiswap::new(
         OwnerRole::None,
+        rule!(deny_all),
         resource_address1,
         resource_address2,
+        30,
         package_address,
         &mut env,
     )?;
@@ -75,3 +79,541 @@ fn reading_and_asserting_against_radiswap_pool_state() -> Result<(), RuntimeErro
 
     Ok(())
 }
+
+#[test]
+fn swap_fee_is_retained_by_the_pool() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address = Package::compile_and_publish(this_package!(), &mut env)?;
+
+    let mut bucket1 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(110, &mut env)?;
+    let bucket2 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(100, &mut env)?;
+
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+
+    let mut radiswap = Radiswap::new(
+        OwnerRole::None,
+        rule!(deny_all),
+        resource_address1,
+        resource_address2,
+        30,
+        package_address,
+        &mut env,
+    )?;
+
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    let _ = radiswap.add_liquidity(bucket1, bucket2, &mut env)?;
+
+    // Act
+    let output_bucket = radiswap.swap(input_bucket, &mut env)?;
+    let radiswap_state = env.read_component_state::<RadiswapState, _>(radiswap)?;
+
+    let VersionedTwoResourcePoolState::V1(TwoResourcePoolSubstate {
+        vaults: [(_, vault1), (_, vault2)],
+        ..
+    }) = env.read_component_state(radiswap_state.pool_component)?;
+
+    // Assert
+    // Without a fee the output would have been 10 * 100 / 110.
+    let output_amount = output_bucket.amount(&mut env)?;
+    assert!(output_amount < dec!("9.090909090909090909"));
+
+    // The fee stays in the pool, so the product of the reserves must have grown.
+    let amount1 = vault1.amount(&mut env)?;
+    let amount2 = vault2.amount(&mut env)?;
+    assert!(amount1.checked_mul(amount2).unwrap() > dec!("10000"));
+
+    Ok(())
+}
+
+#[test]
+fn fee_above_the_hard_cap_is_rejected() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address = Package::compile_and_publish(this_package!(), &mut env)?;
+
+    let bucket1 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(100, &mut env)?;
+    let bucket2 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(100, &mut env)?;
+
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+
+    let mut radiswap = Radiswap::new(
+        OwnerRole::None,
+        rule!(deny_all),
+        resource_address1,
+        resource_address2,
+        30,
+        package_address,
+        &mut env,
+    )?;
+
+    // Act
+    // The auth module is disabled so that the owner-only setter can be called directly.
+    env.disable_auth_module();
+    let within_cap = radiswap.set_fee(100, &mut env);
+    let above_cap = radiswap.set_fee(1001, &mut env);
+
+    // Assert
+    assert!(within_cap.is_ok());
+    assert!(above_cap.is_err());
+    assert_eq!(radiswap.get_fee(&mut env)?, 100);
+
+    Ok(())
+}
+
+#[test]
+fn swap_exact_output_returns_unused_input() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 0)?;
+    let input_bucket = bucket1.take(dec!("20"), &mut env)?;
+
+    // Act
+    let (output_bucket, change_bucket) =
+        radiswap.swap_exact_output(input_bucket, dec!("10"), Epoch::of(100), &mut env)?;
+
+    // Assert
+    // 10 * 100 / 90 of the input is needed to take 10 out of a pool of 100 by 100.
+    assert_eq!(output_bucket.amount(&mut env)?, dec!("10"));
+    let change_amount = change_bucket.amount(&mut env)?;
+    assert!(change_amount > dec!("8.88") && change_amount < dec!("8.89"));
+
+    Ok(())
+}
+
+#[test]
+fn swap_exact_input_below_minimum_output_fails() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 0)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // Act
+    // The pool only gives 10 * 100 / 110 for an input of 10.
+    let result = radiswap.swap_exact_input(input_bucket, dec!("10"), Epoch::of(100), &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn swap_after_deadline_fails() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 0)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    env.set_current_epoch(Epoch::of(10));
+
+    // Act
+    let result = radiswap.swap_exact_input(input_bucket, dec!("0"), Epoch::of(9), &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn quotes_match_swap_execution() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // Act
+    let spot_price = radiswap.spot_price(resource_address1, &mut env)?;
+    let price_impact = radiswap.price_impact(resource_address1, dec!("10"), &mut env)?;
+    let quoted_output = radiswap.get_amount_out(resource_address1, dec!("10"), &mut env)?;
+    let output_bucket = radiswap.swap(input_bucket, &mut env)?;
+    let quoted_input = radiswap.get_amount_in(resource_address2, dec!("5"), &mut env)?;
+
+    // Assert
+    assert_eq!(spot_price, dec!("1"));
+    assert!(price_impact > dec!("0") && price_impact < dec!("0.1"));
+    assert_eq!(output_bucket.amount(&mut env)?, quoted_output);
+    assert!(quoted_input > dec!("5"));
+
+    Ok(())
+}
+
+#[test]
+fn price_impact_of_zero_input_fails() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (radiswap, bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+
+    // Act
+    let result = radiswap.price_impact(resource_address1, dec!("0"), &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn observe_returns_time_weighted_average_price() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, bucket2) = new_funded_radiswap(&mut env, 0)?;
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // Act
+    // The price stays at 1 until epoch 100 when the swap moves it, and then it stays unchanged
+    // until epoch 110.
+    env.set_current_epoch(Epoch::of(100));
+    let _ = radiswap.swap(input_bucket, &mut env)?;
+    let spot_price1 = radiswap.spot_price(resource_address1, &mut env)?;
+    let spot_price2 = radiswap.spot_price(resource_address2, &mut env)?;
+    env.set_current_epoch(Epoch::of(110));
+    let average_prices = radiswap.observe(10, &mut env)?;
+    let too_long_window = radiswap.observe(1000, &mut env);
+
+    // Assert
+    assert_eq!(average_prices[&resource_address1], spot_price1);
+    assert_eq!(average_prices[&resource_address2], spot_price2);
+    assert!(too_long_window.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn stable_swap_curve_has_low_slippage_near_the_peg() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let package_address = Package::compile_and_publish(this_package!(), &mut env)?;
+
+    let mut bucket1 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(110, &mut env)?;
+    let bucket2 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(100, &mut env)?;
+
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+
+    let mut radiswap = Radiswap::new_stable(
+        OwnerRole::None,
+        rule!(deny_all),
+        resource_address1,
+        resource_address2,
+        0,
+        dec!("100"),
+        package_address,
+        &mut env,
+    )?;
+
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    let _ = radiswap.add_liquidity(bucket1, bucket2, &mut env)?;
+
+    // Act
+    let quoted_output = radiswap.get_amount_out(resource_address1, dec!("10"), &mut env)?;
+    let output_bucket = radiswap.swap(input_bucket, &mut env)?;
+
+    // Assert
+    // The constant-product curve would only give 10 * 100 / 110 for the same input.
+    let output_amount = output_bucket.amount(&mut env)?;
+    assert_eq!(output_amount, quoted_output);
+    assert!(output_amount > dec!("9.99") && output_amount < dec!("10"));
+
+    Ok(())
+}
+
+#[test]
+fn flash_swap_repaid_with_fee_succeeds() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, mut bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // Act
+    let (mut borrowed_bucket, flash_swap_receipt) =
+        radiswap.flash_swap(resource_address2, dec!("10"), &mut env)?;
+    // The fee of 0.3% is charged on the whole repayment of 10.04.
+    let fee_bucket = bucket2.take(dec!("0.04"), &mut env)?;
+    borrowed_bucket.put(fee_bucket, &mut env)?;
+    radiswap.repay_flash_swap(borrowed_bucket, flash_swap_receipt, &mut env)?;
+
+    // Assert
+    // Once the flash swap is repaid the pool can be used again.
+    let output_bucket = radiswap.swap(input_bucket, &mut env)?;
+    assert!(output_bucket.amount(&mut env)? > Decimal::ZERO);
+
+    Ok(())
+}
+
+#[test]
+fn flash_swap_repaid_without_fee_fails() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, _bucket1, bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+
+    // Act
+    let (borrowed_bucket, flash_swap_receipt) =
+        radiswap.flash_swap(resource_address2, dec!("10"), &mut env)?;
+    let result = radiswap.repay_flash_swap(borrowed_bucket, flash_swap_receipt, &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn contribution_during_a_flash_swap_is_not_counted_as_repayment() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, mut bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+    let radiswap_state = env.read_component_state::<RadiswapState, _>(radiswap)?;
+    let pool_address = radiswap_state.pool_component.address();
+
+    // Act
+    let (_borrowed_bucket, flash_swap_receipt) =
+        radiswap.flash_swap(resource_address2, dec!("10"), &mut env)?;
+    // The pool is contributed to directly, in the ratio of its depleted reserves of 100 to 90,
+    // and the flash swap is then repaid with nothing.
+    let contribution1 = bucket1.take(dec!("100"), &mut env)?;
+    let contribution2 = bucket2.take(dec!("90"), &mut env)?;
+    let output = env.call_method(
+        pool_address.as_node_id(),
+        TWO_RESOURCE_POOL_CONTRIBUTE_IDENT,
+        scrypto_encode(&TwoResourcePoolContributeInput {
+            buckets: (contribution1, contribution2),
+        })
+        .unwrap(),
+    )?;
+    let _pool_units: TwoResourcePoolContributeOutput = scrypto_decode(&output).unwrap();
+    let empty_repayment = bucket2.take(dec!("0"), &mut env)?;
+    let result = radiswap.repay_flash_swap(empty_repayment, flash_swap_receipt, &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn pool_cannot_be_swapped_against_during_a_flash_swap() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address2 = bucket2.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    let (_borrowed_bucket, _flash_swap_receipt) =
+        radiswap.flash_swap(resource_address2, dec!("10"), &mut env)?;
+
+    // Act
+    let result = radiswap.swap(input_bucket, &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn protocol_fee_share_is_diverted_to_the_treasury() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // The auth module is disabled so that the owner-only methods can be called directly.
+    env.disable_auth_module();
+    radiswap.set_protocol_fee_share(5000, &mut env)?;
+
+    // Act
+    let _ = radiswap.swap(input_bucket, &mut env)?;
+    let (protocol_fees1, protocol_fees2) = radiswap.collect_protocol_fees(&mut env)?;
+
+    // Assert
+    // Half of the 0.3% fee on an input of 10.
+    assert_eq!(protocol_fees1.amount(&mut env)?, dec!("0.015"));
+    assert_eq!(protocol_fees2.amount(&mut env)?, dec!("0"));
+
+    Ok(())
+}
+
+#[test]
+fn protocol_fee_share_above_the_hard_cap_is_rejected() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, _bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    env.disable_auth_module();
+
+    // Act
+    let result = radiswap.set_protocol_fee_share(5001, &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn paused_pool_refuses_swaps_but_allows_removing_liquidity() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, mut bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let liquidity1 = bucket1.take(dec!("10"), &mut env)?;
+    let liquidity2 = bucket2.take(dec!("10"), &mut env)?;
+    let (pool_units, _) = radiswap.add_liquidity(liquidity1, liquidity2, &mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // The auth module is disabled so that the pauser-only methods can be called directly.
+    env.disable_auth_module();
+    radiswap.pause(&mut env)?;
+
+    // Act
+    let removed = radiswap.remove_liquidity(pool_units, &mut env);
+    let swapped = radiswap.swap(input_bucket, &mut env);
+
+    // Assert
+    assert!(removed.is_ok());
+    assert!(swapped.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn circuit_breaker_rejects_large_price_moves() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 0)?;
+    let small_input = bucket1.take(dec!("1"), &mut env)?;
+    let large_input = bucket1.take(dec!("10"), &mut env)?;
+
+    env.disable_auth_module();
+    radiswap.set_max_price_move(Some(500), &mut env)?;
+
+    // Act
+    // An input of 1 moves the price by about 2%, a further input of 10 by about 20%.
+    let small_swap = radiswap.swap(small_input, &mut env);
+    let large_swap = radiswap.swap(large_input, &mut env);
+
+    // Assert
+    assert!(small_swap.is_ok());
+    assert!(large_swap.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn zap_in_contributes_a_single_resource() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+
+    // Act
+    let (pool_units, change) = radiswap.zap_in(input_bucket, dec!("4.8"), &mut env)?;
+
+    // Assert
+    // About 4.888 of the input is swapped for about 4.647 of the other resource, which is then
+    // contributed along with the remaining 5.112 to a pool holding 104.888 and 95.353, giving
+    // 100 * 5.112 / 104.888 pool units.
+    let pool_units_amount = pool_units.amount(&mut env)?;
+    assert!(pool_units_amount > dec!("4.87355") && pool_units_amount < dec!("4.87356"));
+    // The swap is sized so that the pool takes both resources in full, bar rounding.
+    if let Some(change) = change {
+        assert!(change.amount(&mut env)? < dec!("0.000000000001"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn zap_out_returns_a_single_resource() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    let (pool_units, _) = radiswap.zap_in(input_bucket, dec!("0"), &mut env)?;
+
+    // Act
+    let output_bucket = radiswap.zap_out(pool_units, resource_address1, dec!("9.9"), &mut env)?;
+
+    // Assert
+    // Going in and back out costs two swap fees on about half of the amount.
+    assert_eq!(output_bucket.resource_address(&mut env)?, resource_address1);
+    let output_amount = output_bucket.amount(&mut env)?;
+    assert!(output_amount > dec!("9.9") && output_amount < dec!("10"));
+
+    Ok(())
+}
+
+#[test]
+fn zap_out_below_minimum_output_fails() -> Result<(), RuntimeError> {
+    // Arrange
+    let mut env = TestEnvironment::new();
+    let (mut radiswap, mut bucket1, _bucket2) = new_funded_radiswap(&mut env, 30)?;
+    let resource_address1 = bucket1.resource_address(&mut env)?;
+    let input_bucket = bucket1.take(dec!("10"), &mut env)?;
+    let (pool_units, _) = radiswap.zap_in(input_bucket, dec!("0"), &mut env)?;
+
+    // Act
+    // Going in and back out loses some of the 10 to the swap fees.
+    let result = radiswap.zap_out(pool_units, resource_address1, dec!("10"), &mut env);
+
+    // Assert
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+/// Instantiates a Radiswap pool holding 100 of each of its resources and returns it along with 100
+/// more of each resource for the test to trade with.
+fn new_funded_radiswap(
+    env: &mut TestEnvironment,
+    fee_bps: u16,
+) -> Result<(Radiswap, Bucket, Bucket), RuntimeError> {
+    let package_address = Package::compile_and_publish(this_package!(), env)?;
+
+    let mut bucket1 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(200, env)?;
+    let mut bucket2 = ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(18)
+        .mint_initial_supply(200, env)?;
+
+    let resource_address1 = bucket1.resource_address(env)?;
+    let resource_address2 = bucket2.resource_address(env)?;
+
+    let mut radiswap = Radiswap::new(
+        OwnerRole::None,
+        rule!(deny_all),
+        resource_address1,
+        resource_address2,
+        fee_bps,
+        package_address,
+        env,
+    )?;
+
+    let liquidity1 = bucket1.take(dec!("100"), env)?;
+    let liquidity2 = bucket2.take(dec!("100"), env)?;
+    let _ = radiswap.add_liquidity(liquidity1, liquidity2, env)?;
+
+    Ok((radiswap, bucket1, bucket2))
+}
@@ -0,0 +1,312 @@
+use radiswap::OrderPlacedEvent;
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+mod common;
+use common::{find_event, instantiate_funded_radiswap};
+
+#[test]
+fn take_profit_order_fills_once_the_price_crosses_the_target() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which trades, fills orders and provides liquidity all at once
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (pool, order_book, orders) = instantiate_order_book(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `place_take_profit_order` method, selling 10 A once A is worth 1.2 B.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(order_book, "place_take_profit_order", |lookup| {
+            (lookup.bucket("input"), dec!("1.2"))
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let order_id =
+        find_event::<OrderPlacedEvent>(&test_runner, &receipt, "OrderPlacedEvent").order_id;
+
+    // The price of A is still 1, so the order cannot be filled yet.
+    let manifest = ManifestBuilder::new()
+        .call_method(order_book, "fill", manifest_args!(order_id.clone()))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_failure();
+
+    // Buying A with 20 B brings the price of A to 1.44 B.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_b, dec!(20))
+        .take_all_from_worktop(resource_b, "input")
+        .call_method_with_name_lookup(pool, "swap", |lookup| (lookup.bucket("input"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    let balance_a = test_runner
+        .account_balance(account_component, resource_a)
+        .unwrap();
+    let balance_b = test_runner
+        .account_balance(account_component, resource_b)
+        .unwrap();
+
+    // Test the `fill` method, the keeper earns 1% of the input of the order.
+    let manifest = ManifestBuilder::new()
+        .call_method(order_book, "fill", manifest_args!(order_id))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(balance_a.checked_add(dec!("0.1")).unwrap())
+    );
+
+    // Test the `claim` method, the 9.9 A left after the tip are worth at least 1.2 B each.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, orders, dec!(1))
+        .take_all_from_worktop(orders, "order")
+        .call_method_with_name_lookup(order_book, "claim", |lookup| (lookup.bucket("order"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+
+    let claimed_b = test_runner
+        .account_balance(account_component, resource_b)
+        .unwrap()
+        .checked_sub(balance_b)
+        .unwrap();
+    assert!(claimed_b >= dec!("11.88"));
+}
+
+#[test]
+fn cancelled_order_returns_the_escrowed_input() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (_pool, order_book, orders) = instantiate_order_book(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+    );
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(order_book, "place_stop_order", |lookup| {
+            (lookup.bucket("input"), dec!("0.8"), 500u16)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `cancel` method.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, orders, dec!(1))
+        .take_all_from_worktop(orders, "order")
+        .call_method_with_name_lookup(order_book, "cancel", |lookup| (lookup.bucket("order"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(900))
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, orders),
+        Some(dec!(0))
+    );
+}
+
+#[test]
+fn stop_order_fills_above_its_floor_only() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account, which trades, fills orders and provides liquidity all at once
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let (pool, order_book, orders) = instantiate_order_book(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+    );
+
+    // Test the `place_stop_order` method, selling 1 A once A is worth 0.9 B or less, but never for
+    // less than 0.855 B each.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(1))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(order_book, "place_stop_order", |lookup| {
+            (lookup.bucket("input"), dec!("0.9"), 500u16)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    let order_id =
+        find_event::<OrderPlacedEvent>(&test_runner, &receipt, "OrderPlacedEvent").order_id;
+
+    // Selling 20 A brings the price of A down to about 0.69 B, but filling the order in the same
+    // transaction would sell it at about 0.69 B too, below its floor.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(20))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(pool, "swap", |lookup| (lookup.bucket("input"),))
+        .call_method(order_book, "fill", manifest_args!(order_id.clone()))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_failure();
+
+    // Selling 6 A brings the price of A down to about 0.89 B.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(6))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(pool, "swap", |lookup| (lookup.bucket("input"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    let balance_b = test_runner
+        .account_balance(account_component, resource_b)
+        .unwrap();
+
+    // Test the `fill` and `claim` methods, the 0.99 A left after the tip sell for about 0.87 B.
+    let manifest = ManifestBuilder::new()
+        .call_method(order_book, "fill", manifest_args!(order_id))
+        .withdraw_from_account(account_component, orders, dec!(1))
+        .take_all_from_worktop(orders, "order")
+        .call_method_with_name_lookup(order_book, "claim", |lookup| (lookup.bucket("order"),))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+
+    let claimed_b = test_runner
+        .account_balance(account_component, resource_b)
+        .unwrap()
+        .checked_sub(balance_b)
+        .unwrap();
+    assert!(claimed_b >= dec!("0.84645") && claimed_b < dec!("0.9"));
+}
+
+#[test]
+fn keeper_tip_above_the_cap_is_rejected() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let pool = instantiate_funded_radiswap(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+        0,
+    );
+
+    // Test the `instantiate_order_book` function with a keeper tip of 1.01%.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapLimitOrderBook",
+            "instantiate_order_book",
+            manifest_args!(pool, 101u16),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+}
+
+/// Instantiates a fee-less Radiswap pool holding 100 of each resource and an order book over it
+/// with a keeper tip of 1%. Returns the pool, the order book and the resource of its order NFTs.
+fn instantiate_order_book(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    package_address: PackageAddress,
+    resource_address1: ResourceAddress,
+    resource_address2: ResourceAddress,
+) -> (ComponentAddress, ComponentAddress, ResourceAddress) {
+    let pool = instantiate_funded_radiswap(
+        test_runner,
+        public_key,
+        account_component,
+        package_address,
+        resource_address1,
+        resource_address2,
+        0,
+    );
+
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapLimitOrderBook",
+            "instantiate_order_book",
+            manifest_args!(pool, 100u16),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let commit = receipt.expect_commit_success();
+
+    (
+        pool,
+        commit.new_component_addresses()[0],
+        commit.new_resource_addresses()[0],
+    )
+}
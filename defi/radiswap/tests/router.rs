@@ -0,0 +1,137 @@
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+mod common;
+use common::instantiate_funded_radiswap;
+
+#[test]
+fn swap_along_two_hop_path() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let owner_badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Create the three resources of the path, only the first two and the last two are paired.
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_c = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    let pool_ab = instantiate_funded_radiswap(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+        0,
+    );
+    let pool_bc = instantiate_funded_radiswap(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_b,
+        resource_c,
+        0,
+    );
+
+    // Instantiate the router and register both pools.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapRouter",
+            "instantiate_router",
+            manifest_args!(OwnerRole::Fixed(rule!(require(owner_badge.clone())))),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    let router = receipt.expect_commit_success().new_component_addresses()[0];
+
+    let manifest = ManifestBuilder::new()
+        .call_method(router, "register_pool", manifest_args!(pool_ab))
+        .call_method(router, "register_pool", manifest_args!(pool_bc))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `swap_along_path` method.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(router, "swap_along_path", |lookup| {
+            (
+                lookup.bucket("input"),
+                vec![resource_a, resource_b, resource_c],
+                dec!(8),
+            )
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge.clone()]);
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // 10 A gives 10 * 100 / 110 B, which in turn gives a little over 8.33 C.
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_a),
+        Some(dec!(890))
+    );
+    let balance_c = test_runner
+        .account_balance(account_component, resource_c)
+        .unwrap();
+    assert!(balance_c > dec!(908) && balance_c < dec!(909));
+}
+
+#[test]
+fn registering_a_pool_requires_the_owner() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create two accounts, only the first one owns the router
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (other_public_key, _other_private_key, _other_account_component) =
+        test_runner.new_allocated_account();
+    let owner_badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let pool_ab = instantiate_funded_radiswap(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        package_address,
+        resource_a,
+        resource_b,
+        0,
+    );
+
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RadiswapRouter",
+            "instantiate_router",
+            manifest_args!(OwnerRole::Fixed(rule!(require(owner_badge.clone())))),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![owner_badge]);
+    let router = receipt.expect_commit_success().new_component_addresses()[0];
+
+    // Test the `register_pool` method without the owner's signature.
+    let manifest = ManifestBuilder::new()
+        .call_method(router, "register_pool", manifest_args!(pool_ab))
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&other_public_key)],
+    );
+    receipt.expect_commit_failure();
+}
@@ -1,37 +1,167 @@
 use scrypto::prelude::*;
 
+/// Issued by the compliance admin to an approved investor, straight into the investor's account.  The badge can not
+/// be withdrawn from that account, and regulated tokens may only be sent to an account whose owner presents the badge
+/// issued to it.  Holders who were never verified, or whose badge was recalled, keep their tokens but may have their
+/// vault frozen by the freeze admin.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct KycBadge {
+    pub investor_name: String,
+    /// The account the badge was issued to, which is the only account it vouches for
+    pub account: ComponentAddress,
+    pub approved_at: Epoch,
+}
+
+/// One stage in the lifecycle of the token.  The stages are walked through in order by `advance_stage`, and the last
+/// one is final: once it is reached the token is no longer regulated and its behavior is locked for good.
+#[derive(ScryptoSbor, ManifestSbor, Clone)]
+pub struct StageDefinition {
+    /// The price of one token in XRD during this stage
+    pub price: Decimal,
+    /// Whether tokens may be minted beyond the current supply during this stage
+    pub mintable: bool,
+    /// Whether the vaults of holders may be frozen during this stage
+    pub freezable: bool,
+    /// Whether tokens may be recalled from holders during this stage
+    pub recallable: bool,
+    /// The most tokens that may exist during this stage, if minting is allowed at all
+    pub max_supply: Option<Decimal>,
+    /// Shown in the "stage" metadata of the token during this stage
+    pub description: String,
+    /// The most tokens that may be sold during this stage
+    pub sale_cap: Option<Decimal>,
+    /// The most tokens that a single buyer may buy during this stage
+    pub purchase_limit: Option<Decimal>,
+    /// The epochs during which tokens may be bought in this stage, from the first included to the second excluded
+    pub sale_window: Option<(Epoch, Epoch)>,
+}
+
+/// What a compliance officer badge entitles its holder to do
+#[derive(ScryptoSbor, ManifestSbor, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OfficerDuty {
+    /// Recall holdings of the token
+    Recall,
+    /// Freeze and unfreeze vaults of the token
+    Freeze,
+}
+
+/// Appointed by the general admin, and presented by a compliance officer to recall or freeze holdings of the token,
+/// depending on the duty the officer was appointed to.
+#[derive(NonFungibleData, ScryptoSbor)]
+pub struct ComplianceOfficer {
+    pub name: String,
+    pub duty: OfficerDuty,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct VaultRecalledEvent {
+    pub vault_id: InternalAddress,
+    /// The amount taken out of the vault, as returned in the recalled bucket
+    pub amount: Decimal,
+    pub reason: String,
+    pub acting_badge: NonFungibleGlobalId,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct VaultFrozenEvent {
+    pub vault_id: InternalAddress,
+    pub reason: String,
+    pub acting_badge: NonFungibleGlobalId,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+pub struct VaultUnfrozenEvent {
+    pub vault_id: InternalAddress,
+    pub reason: String,
+    pub acting_badge: NonFungibleGlobalId,
+}
+
 #[blueprint]
+#[events(VaultRecalledEvent, VaultFrozenEvent, VaultUnfrozenEvent)]
 mod regulated_token {
     enable_method_auth! {
         roles {
             freeze_admin => updatable_by: [];
             general_admin => updatable_by: [];
+            compliance_admin => updatable_by: [];
         },
         methods {
             toggle_transfer_freeze => restrict_to: [freeze_admin];
+            issue_kyc_badge => restrict_to: [compliance_admin];
+            get_kyc_badge_address => PUBLIC;
+            appoint_officer => restrict_to: [general_admin];
+            // The officer badge presented to these is checked by the methods themselves, so that the events they
+            // emit name the badge which authorised the call
+            recall_from => PUBLIC;
+            freeze_vault => PUBLIC;
+            unfreeze_vault => PUBLIC;
             collect_payments => restrict_to: [general_admin];
             advance_stage => restrict_to: [general_admin];
             get_current_stage => PUBLIC;
             buy_token => PUBLIC;
+            transfer => PUBLIC;
         }
     }
     struct RegulatedToken {
         token_supply: Vault,
         collected_xrd: Vault,
         current_stage: u8,
+        stages: Vec<StageDefinition>,
+        /// The tokens sold so far during the current stage
+        sold_in_stage: Decimal,
+        /// The tokens bought by every buyer during every stage, keyed by the stage and the buyer's KYC badge
+        purchases: KeyValueStore<(u8, NonFungibleLocalId), Decimal>,
+        /// The KYC badge issued to every approved account.  An account is issued one badge at most, so that the
+        /// purchase limit of a stage applies to the account as a whole
+        kyc_badges: KeyValueStore<ComponentAddress, NonFungibleLocalId>,
         admin_badge_address: ResourceAddress,
         freeze_admin_badge_address: ResourceAddress,
+        kyc_badge_manager: ResourceManager,
+        officer_badge_manager: ResourceManager,
     }
 
     impl RegulatedToken {
+        /// Creates the token with the given initial supply, in the first of the given stages.  At least two stages
+        /// must be given, the last of which is final.
         pub fn instantiate_regulated_token(
-        ) -> (Global<RegulatedToken>, FungibleBucket, FungibleBucket) {
+            initial_supply: Decimal,
+            stages: Vec<StageDefinition>,
+        ) -> (
+            Global<RegulatedToken>,
+            FungibleBucket,
+            FungibleBucket,
+            FungibleBucket,
+        ) {
+            assert!(
+                stages.len() >= 2 && stages.len() <= u8::MAX as usize,
+                "Between 2 and {} stages must be defined",
+                u8::MAX
+            );
+            for stage in &stages {
+                assert!(
+                    stage.price > Decimal::ZERO,
+                    "The price of every stage must be positive"
+                );
+            }
+            let final_stage = stages.last().unwrap();
+            assert!(
+                !final_stage.mintable && !final_stage.freezable && !final_stage.recallable,
+                "The final stage must be neither mintable, freezable nor recallable, as it locks those behaviors off"
+            );
+            let first_stage = stages[0].clone();
+            if let Some(max_supply) = first_stage.max_supply {
+                assert!(
+                    initial_supply <= max_supply,
+                    "Initial supply exceeds the max supply of the first stage"
+                );
+            }
+
             // We are allocating a ComponentAddress used for our actor virtual badge and provide
             // minting & transfer authority to our component.
             let (address_reservation, component_address) =
                 Runtime::allocate_component_address(RegulatedToken::blueprint_id());
 
-            // Creating two resources we will use as badges and return to our instantiator
+            // Creating three resources we will use as badges and return to our instantiator
             let general_admin = ResourceBuilder::new_fungible(OwnerRole::None)
                 .divisibility(DIVISIBILITY_NONE)
                 .metadata(metadata! (
@@ -58,11 +188,80 @@ mod regulated_token {
                 ))
                 .mint_initial_supply(1);
 
-            // Next we will create our regulated token with an initial fixed supply of 100 and the appropriate permissions
+            let compliance_admin = ResourceBuilder::new_fungible(OwnerRole::None)
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata(metadata! (
+                    init {
+                        "name" => "RegulatedToken compliance badge".to_string(), locked;
+                    }
+                ))
+                .burn_roles(burn_roles!(
+                    burner => rule!(allow_all);
+                    burner_updater => rule!(deny_all);
+                ))
+                .mint_initial_supply(1);
+
+            // The KYC badges are soulbound: they stay in the account they were issued to, unless the compliance
+            // admin recalls them to revoke an investor's approval
+            let kyc_badge_manager =
+                ResourceBuilder::new_ruid_non_fungible::<KycBadge>(OwnerRole::None)
+                    .metadata(metadata! (
+                        init {
+                            "name" => "RegulatedToken KYC badge".to_string(), locked;
+                        }
+                    ))
+                    .mint_roles(mint_roles!(
+                        minter => rule!(require(global_caller(component_address)));
+                        minter_updater => rule!(deny_all);
+                    ))
+                    .withdraw_roles(withdraw_roles!(
+                        withdrawer => rule!(deny_all);
+                        withdrawer_updater => rule!(deny_all);
+                    ))
+                    .recall_roles(recall_roles!(
+                        recaller => rule!(require(compliance_admin.resource_address()));
+                        recaller_updater => rule!(deny_all);
+                    ))
+                    .burn_roles(burn_roles!(
+                        burner => rule!(require(compliance_admin.resource_address()));
+                        burner_updater => rule!(deny_all);
+                    ))
+                    .create_with_no_initial_supply();
+
+            // Every compliance officer gets their own badge, so that the events of the actions they take record who
+            // took them
+            let officer_badge_manager =
+                ResourceBuilder::new_ruid_non_fungible::<ComplianceOfficer>(OwnerRole::None)
+                    .metadata(metadata! (
+                        init {
+                            "name" => "RegulatedToken compliance officer badge".to_string(), locked;
+                        }
+                    ))
+                    .mint_roles(mint_roles!(
+                        minter => rule!(require(global_caller(component_address)));
+                        minter_updater => rule!(deny_all);
+                    ))
+                    .recall_roles(recall_roles!(
+                        recaller => rule!(require(general_admin.resource_address()));
+                        recaller_updater => rule!(deny_all);
+                    ))
+                    .burn_roles(burn_roles!(
+                        burner => rule!(allow_all);
+                        burner_updater => rule!(deny_all);
+                    ))
+                    .create_with_no_initial_supply();
+
+            // Next we will create our regulated token with its initial supply and the permissions of the first stage
             let access_rule: AccessRule = rule!(
                 require(general_admin.resource_address())
                     || require(global_caller(component_address))
             );
+            let freeze_rule: AccessRule = rule!(
+                require(freeze_admin.resource_address())
+                    || require(global_caller(component_address))
+            );
+            // Only the component may mint, so that the max supply of each stage can not be bypassed
+            let mint_rule: AccessRule = rule!(require(global_caller(component_address)));
             let regulated_tokens = ResourceBuilder::new_fungible(OwnerRole::None)
                 .divisibility(DIVISIBILITY_MAXIMUM)
                 .metadata(metadata! (
@@ -75,11 +274,11 @@ mod regulated_token {
                     init {
                         "name" => "Regulo".to_string(), locked;
                         "symbol" => "REG".to_string(), locked;
-                        "stage" => "Stage 1 - Fixed supply, may be restricted transfer".to_string(), updatable;
+                        "stage" => first_stage.description.clone(), updatable;
                     }
                 ))
                 .freeze_roles(freeze_roles!(
-                    freezer => rule!(require(freeze_admin.resource_address()));
+                    freezer => Self::stage_rule(first_stage.freezable, freeze_rule);
                     freezer_updater => access_rule.clone();
                 ))
                 .withdraw_roles(withdraw_roles!(
@@ -87,37 +286,58 @@ mod regulated_token {
                     withdrawer_updater => access_rule.clone();
                 ))
                 .recall_roles(recall_roles!(
-                    recaller => access_rule.clone();
+                    recaller => Self::stage_rule(first_stage.recallable, access_rule.clone());
                     recaller_updater => access_rule.clone();
                 ))
                 .mint_roles(mint_roles!(
-                    minter => rule!(deny_all);
-                    minter_updater => access_rule.clone();
+                    minter => Self::stage_rule(first_stage.mintable, mint_rule.clone());
+                    minter_updater => mint_rule;
+                ))
+                // The deposit rule is checked against whoever makes the deposit, not against the account receiving it,
+                // so only the component may deposit the token.  It does so in `buy_token` and `transfer`, once the
+                // owner of the receiving account has presented the KYC badge issued to that account
+                .deposit_roles(deposit_roles!(
+                    depositor => rule!(
+                        require(general_admin.resource_address())
+                            || require(global_caller(component_address))
+                    );
+                    depositor_updater => access_rule.clone();
                 ))
-                .mint_initial_supply(100);
+                .mint_initial_supply(initial_supply);
+
+            // The component does not exist yet, so the admin badge authorizes putting the initial supply away
+            let token_supply =
+                general_admin.authorize_with_all(|| Vault::with_bucket(regulated_tokens.into()));
 
             let component = Self {
-                token_supply: Vault::with_bucket(regulated_tokens.into()),
+                token_supply,
                 collected_xrd: Vault::new(XRD),
                 current_stage: 1,
+                stages,
+                sold_in_stage: Decimal::ZERO,
+                purchases: KeyValueStore::new(),
+                kyc_badges: KeyValueStore::new(),
                 admin_badge_address: general_admin.resource_address(),
                 freeze_admin_badge_address: freeze_admin.resource_address(),
+                kyc_badge_manager,
+                officer_badge_manager,
             }
             .instantiate()
             .prepare_to_globalize(OwnerRole::None)
             .roles(roles!(
                 freeze_admin => rule!(require(freeze_admin.resource_address()));
                 general_admin => rule!(require(general_admin.resource_address()));
+                compliance_admin => rule!(require(compliance_admin.resource_address()));
             ))
             .with_address(address_reservation)
             .globalize();
 
-            (component, general_admin, freeze_admin)
+            (component, general_admin, freeze_admin, compliance_admin)
         }
 
         /// The freeze admin badge may be used to freeze or unfreeze consumer transfers of the supply
         pub fn toggle_transfer_freeze(&self, set_frozen: bool) {
-            // Note that this operation will fail if the token has reached stage 3 and the token behavior has been locked
+            // Note that this operation will fail if the token has reached its final stage and the token behavior has been locked
             let token_resource_manager = self.token_supply.resource_manager();
 
             if set_frozen {
@@ -130,6 +350,119 @@ mod regulated_token {
             }
         }
 
+        /// The compliance admin badge may be used to approve an investor.  The KYC badge is deposited in the
+        /// investor's account, which may receive regulated tokens from then on.  Every account holds one badge at
+        /// most, a new one may only be issued once the compliance admin has recalled and burned the previous one
+        pub fn issue_kyc_badge(&mut self, investor_name: String, mut account: Global<Account>) {
+            if let Some(kyc_badge_id) = self.kyc_badges.get(&account.address()) {
+                assert!(
+                    !self.kyc_badge_manager.non_fungible_exists(&kyc_badge_id),
+                    "A KYC badge was already issued to this account"
+                );
+            }
+
+            let kyc_badge = self.kyc_badge_manager.mint_ruid_non_fungible(KycBadge {
+                investor_name,
+                account: account.address(),
+                approved_at: Runtime::current_epoch(),
+            });
+            self.kyc_badges.insert(
+                account.address(),
+                kyc_badge.as_non_fungible().non_fungible_local_id(),
+            );
+            account.try_deposit_or_abort(kyc_badge, None);
+        }
+
+        pub fn get_kyc_badge_address(&self) -> ResourceAddress {
+            self.kyc_badge_manager.address()
+        }
+
+        /// The general admin badge may be used to appoint a compliance officer to either recall or freeze holdings.
+        /// The general admin may recall the badge of an officer to dismiss them
+        pub fn appoint_officer(&mut self, name: String, duty: OfficerDuty) -> Bucket {
+            self.officer_badge_manager
+                .mint_ruid_non_fungible(ComplianceOfficer { name, duty })
+        }
+
+        /// Recalls the given amount of tokens from a holder's vault, for example to enforce a court order
+        pub fn recall_from(
+            &mut self,
+            vault_id: InternalAddress,
+            amount: Decimal,
+            reason: String,
+            officer_badge: Proof,
+        ) -> Bucket {
+            let acting_badge = self.check_officer_badge(officer_badge, OfficerDuty::Recall);
+
+            // Recalling is a direct access method of the vault.  Our component is a recaller of the regulated token
+            // only, so the engine refuses to recall from the vaults of any other resource
+            let output = ScryptoVmV1Api::object_call_direct(
+                vault_id.as_node_id(),
+                VAULT_RECALL_IDENT,
+                scrypto_encode(&VaultRecallInput { amount }).unwrap(),
+            );
+            let recalled: Bucket = scrypto_decode(&output).unwrap();
+
+            Runtime::emit_event(VaultRecalledEvent {
+                vault_id,
+                amount: recalled.amount(),
+                reason,
+                acting_badge,
+            });
+            recalled
+        }
+
+        /// Freezes a holder's vault, so that nothing can be withdrawn from, deposited to or burned in it.  As with
+        /// recalls, only vaults of the regulated token can be frozen
+        pub fn freeze_vault(
+            &mut self,
+            vault_id: InternalAddress,
+            reason: String,
+            officer_badge: Proof,
+        ) {
+            let acting_badge = self.check_officer_badge(officer_badge, OfficerDuty::Freeze);
+
+            ScryptoVmV1Api::object_call_direct(
+                vault_id.as_node_id(),
+                VAULT_FREEZE_IDENT,
+                scrypto_encode(&VaultFreezeInput {
+                    to_freeze: VaultFreezeFlags::all(),
+                })
+                .unwrap(),
+            );
+
+            Runtime::emit_event(VaultFrozenEvent {
+                vault_id,
+                reason,
+                acting_badge,
+            });
+        }
+
+        /// Lifts a freeze put on a holder's vault by `freeze_vault`
+        pub fn unfreeze_vault(
+            &mut self,
+            vault_id: InternalAddress,
+            reason: String,
+            officer_badge: Proof,
+        ) {
+            let acting_badge = self.check_officer_badge(officer_badge, OfficerDuty::Freeze);
+
+            ScryptoVmV1Api::object_call_direct(
+                vault_id.as_node_id(),
+                VAULT_UNFREEZE_IDENT,
+                scrypto_encode(&VaultUnfreezeInput {
+                    to_unfreeze: VaultFreezeFlags::all(),
+                })
+                .unwrap(),
+            );
+
+            Runtime::emit_event(VaultUnfrozenEvent {
+                vault_id,
+                reason,
+                acting_badge,
+            });
+        }
+
         pub fn get_current_stage(&self) -> u8 {
             info!("Current stage is {}", self.current_stage);
             self.current_stage
@@ -141,43 +474,50 @@ mod regulated_token {
         }
 
         pub fn advance_stage(&mut self) {
-            assert!(self.current_stage <= 2, "Already at final stage");
+            assert!(
+                (self.current_stage as usize) < self.stages.len(),
+                "Already at final stage"
+            );
+            self.current_stage += 1;
+            self.sold_in_stage = Decimal::ZERO;
+            let stage = self.current_stage_definition();
             let token_resource_manager = self.token_supply.resource_manager();
 
-            if self.current_stage == 1 {
-                // Advance to stage 2
-                // Token will still be restricted transfer upon admin demand, but we will mint beyond the initial supply as required
-                self.current_stage = 2;
-
-                // Update token's metadata to reflect the current stage
-                token_resource_manager.set_metadata(
-                    "stage",
-                    "Stage 2 - Unlimited supply, may be restricted transfer".to_string(),
-                );
+            // Update token's metadata to reflect the current stage
+            token_resource_manager.set_metadata("stage", stage.description);
 
-                // Enable minting for the token
-                token_resource_manager.set_mintable(rule!(
+            if (self.current_stage as usize) < self.stages.len() {
+                // Switch minting, freezing and recalling on or off as the stage requires
+                // Restricted transfer stays available upon admin demand
+                let access_rule = rule!(
                     require(self.admin_badge_address)
                         || require(global_caller(Runtime::global_address()))
+                );
+                token_resource_manager.set_mintable(Self::stage_rule(
+                    stage.mintable,
+                    rule!(require(global_caller(Runtime::global_address()))),
+                ));
+                token_resource_manager.set_freezeable(Self::stage_rule(
+                    stage.freezable,
+                    rule!(
+                        require(self.freeze_admin_badge_address)
+                            || require(global_caller(Runtime::global_address()))
+                    ),
                 ));
-                info!("Advanced to stage 2");
+                token_resource_manager
+                    .set_recallable(Self::stage_rule(stage.recallable, access_rule));
+                info!("Advanced to stage {}", self.current_stage);
             } else {
-                // Advance to stage 3
+                // Advance to the final stage
                 // Token will no longer be regulated
                 // Restricted transfer will be permanently turned off, supply will be made permanently immutable
-                self.current_stage = 3;
-
-                // Update token's metadata to reflect the final stage
-                token_resource_manager.set_metadata(
-                    "stage",
-                    "Stage 3 - Unregulated token, fixed supply".to_string(),
-                );
 
                 // Set our behavior appropriately now that the regulated period has ended
                 token_resource_manager.set_mintable(rule!(deny_all));
                 token_resource_manager.set_freezeable(rule!(deny_all));
                 token_resource_manager.set_recallable(rule!(deny_all));
                 token_resource_manager.set_withdrawable(rule!(allow_all));
+                token_resource_manager.set_depositable(rule!(allow_all));
                 token_resource_manager.set_metadata_role("metadata_setter", rule!(deny_all));
                 token_resource_manager
                     .set_metadata_role("metadata_setter_updater", rule!(deny_all));
@@ -185,6 +525,7 @@ mod regulated_token {
                 // Permanently prevent the behavior of the token from changing
                 token_resource_manager.lock_mintable();
                 token_resource_manager.lock_withdrawable();
+                token_resource_manager.lock_depositable();
                 token_resource_manager.lock_freezeable();
                 token_resource_manager.lock_recallable();
                 token_resource_manager.lock_updatable_metadata();
@@ -193,24 +534,85 @@ mod regulated_token {
                 // We will burn our internal badge, and the holders of the other badges may burn them at will
                 // Our badge has the allows everybody to burn, so there's no need to provide a burning authority
 
-                info!("Advanced to stage 3");
+                info!("Advanced to final stage {}", self.current_stage);
             }
         }
 
+        /// Sends regulated tokens to the given account, whose owner must present the KYC badge issued to it.  As only
+        /// the component may deposit the token, this is how holders send it to each other
+        pub fn transfer(
+            &mut self,
+            tokens: Bucket,
+            mut recipient: Global<Account>,
+            recipient_badge: Proof,
+        ) {
+            assert!(
+                tokens.resource_address() == self.token_supply.resource_address(),
+                "Only the regulated token can be transferred"
+            );
+            self.check_kyc_badge(recipient_badge, &recipient);
+
+            recipient.try_deposit_or_abort(tokens, None);
+        }
+
         /// Buy a quantity of tokens, if the supply on-hand is sufficient, or if current rules permit minting additional supply.
         /// The system will *always* allow buyers to purchase available tokens, even when the token transfers are otherwise frozen
-        pub fn buy_token(&mut self, quantity: Decimal, mut payment: Bucket) -> (Bucket, Bucket) {
+        ///
+        /// Buyers identify themselves with a proof of the KYC badge issued to the account the tokens are deposited in, and
+        /// their purchases during the stage are counted against it.  Purchases are rejected outside of the sale window of the
+        /// stage, beyond its sale cap, or beyond the purchase limit of the buyer.  The change of the payment is returned.
+        pub fn buy_token(
+            &mut self,
+            quantity: Decimal,
+            mut payment: Bucket,
+            mut buyer_account: Global<Account>,
+            buyer_badge: Proof,
+        ) -> Bucket {
             assert!(
                 quantity > dec!("0"),
                 "Can't sell you nothing or less than nothing"
             );
+            let buyer_id = self.check_kyc_badge(buyer_badge, &buyer_account);
 
-            // Early birds who buy during stage 1 get a discounted rate
-            let price: Decimal = if self.current_stage == 1 {
-                dec!("50")
-            } else {
-                dec!("100")
-            };
+            // Each stage sets its own price, so early birds may get a discounted rate
+            let stage = self.current_stage_definition();
+            let price: Decimal = stage.price;
+
+            if let Some((sale_start, sale_end)) = stage.sale_window {
+                let current_epoch = Runtime::current_epoch().number();
+                assert!(
+                    current_epoch >= sale_start.number() && current_epoch < sale_end.number(),
+                    "The sale of this stage runs from epoch {} until epoch {}",
+                    sale_start.number(),
+                    sale_end.number()
+                );
+            }
+
+            // Count the purchase against the sale cap of the stage and the purchase limit of the buyer
+            self.sold_in_stage = self.sold_in_stage.checked_add(quantity).unwrap();
+            if let Some(sale_cap) = stage.sale_cap {
+                assert!(
+                    self.sold_in_stage <= sale_cap,
+                    "Purchase would exceed the sale cap of {} for this stage",
+                    sale_cap
+                );
+            }
+            let purchase_key = (self.current_stage, buyer_id);
+            let purchased = self
+                .purchases
+                .get(&purchase_key)
+                .map(|purchased| *purchased)
+                .unwrap_or(Decimal::ZERO)
+                .checked_add(quantity)
+                .unwrap();
+            if let Some(purchase_limit) = stage.purchase_limit {
+                assert!(
+                    purchased <= purchase_limit,
+                    "Buyers may buy at most {} tokens in this stage",
+                    purchase_limit
+                );
+            }
+            self.purchases.insert(purchase_key, purchased);
 
             // Take what we're owed
             self.collected_xrd
@@ -219,24 +621,91 @@ mod regulated_token {
             // Can we fill the desired quantity from current supply?
             let extra_demand = quantity.checked_sub(self.token_supply.amount()).unwrap();
             if extra_demand <= dec!("0") {
-                // Take the required quantity, and return it along with any change
+                // Take the required quantity, and deposit it in the buyer's account
                 // The token may currently be under restricted transfer, so we will authorize our withdrawal
                 let tokens = self.token_supply.take(quantity);
+                buyer_account.try_deposit_or_abort(tokens, None);
 
-                return (tokens, payment);
+                return payment;
             } else {
-                // We will attempt to mint the shortfall
-                // If we are in stage 1 or 3, this action will fail, and it would probably be a good idea to tell the user this
-                // For the purposes of example, we will blindly attempt to mint
-                let mut tokens = self.token_supply.resource_manager().mint(extra_demand);
+                // We will attempt to mint the shortfall, if the current stage allows it
+                assert!(
+                    stage.mintable,
+                    "Not enough supply left, and no more may be minted in this stage"
+                );
+                let token_resource_manager = self.token_supply.resource_manager();
+                let total_supply = token_resource_manager
+                    .total_supply()
+                    .unwrap()
+                    .checked_add(extra_demand)
+                    .unwrap();
+                if let Some(max_supply) = stage.max_supply {
+                    assert!(
+                        total_supply <= max_supply,
+                        "Minting {} more would exceed the max supply of {} for this stage",
+                        extra_demand,
+                        max_supply
+                    );
+                }
+                let mut tokens = token_resource_manager.mint(extra_demand);
 
                 // Combine the new tokens with whatever was left in supply to meet the full quantity
                 let existing_tokens = self.token_supply.take_all();
                 tokens.put(existing_tokens);
 
-                // Return the tokens, along with any change
-                return (tokens, payment);
+                // Deposit the tokens in the buyer's account, and return any change
+                buyer_account.try_deposit_or_abort(tokens, None);
+                return payment;
             }
         }
+
+        fn current_stage_definition(&self) -> StageDefinition {
+            self.stages[self.current_stage as usize - 1].clone()
+        }
+
+        /// The given rule if the stage enables the behavior it governs, otherwise nobody is allowed
+        fn stage_rule(enabled: bool, rule: AccessRule) -> AccessRule {
+            if enabled {
+                rule
+            } else {
+                rule!(deny_all)
+            }
+        }
+
+        /// Checks that the proof is of the KYC badge issued to the given account and returns its local id
+        fn check_kyc_badge(
+            &self,
+            kyc_badge: Proof,
+            account: &Global<Account>,
+        ) -> NonFungibleLocalId {
+            let kyc_badge = kyc_badge.check(self.kyc_badge_manager.address());
+            let kyc_badge = kyc_badge.as_non_fungible().non_fungible::<KycBadge>();
+            assert!(
+                kyc_badge.data().account == account.address(),
+                "The KYC badge was not issued to the account"
+            );
+            kyc_badge.local_id().clone()
+        }
+
+        /// Checks that the proof is of a compliance officer badge appointed to the given duty and returns its global
+        /// id, which is recorded in the events of the action the officer takes
+        fn check_officer_badge(
+            &self,
+            officer_badge: Proof,
+            duty: OfficerDuty,
+        ) -> NonFungibleGlobalId {
+            let officer_badge = officer_badge.check(self.officer_badge_manager.address());
+            let officer = officer_badge
+                .as_non_fungible()
+                .non_fungible::<ComplianceOfficer>();
+            assert!(
+                officer.data().duty == duty,
+                "The officer badge was not appointed to this duty"
+            );
+            NonFungibleGlobalId::new(
+                self.officer_badge_manager.address(),
+                officer.local_id().clone(),
+            )
+        }
     }
 }
@@ -0,0 +1,846 @@
+use radix_engine::errors::{ApplicationError, RuntimeError, SystemModuleError};
+use radix_engine::transaction::TransactionReceipt;
+use regulated_token::{OfficerDuty, StageDefinition, VaultRecalledEvent};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+use transaction::prelude::TransactionManifestV1;
+
+#[test]
+fn tokens_can_only_be_sent_to_verified_accounts() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the issuer, which is also the first investor, the account of a second
+    // investor and the account of someone who was never verified
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (verified_public_key, _verified_private_key, verified_account) =
+        test_runner.new_allocated_account();
+    let (unverified_public_key, _unverified_private_key, unverified_account) =
+        test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+    let verified_badge = NonFungibleGlobalId::from_public_key(&verified_public_key);
+    let unverified_badge = NonFungibleGlobalId::from_public_key(&unverified_public_key);
+
+    let issuance = instantiate_regulated_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        vec![stage(dec!(1)), final_stage()],
+    );
+    for investor_account in [account_component, verified_account] {
+        let receipt = issue_kyc_badge(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            investor_account,
+        );
+        receipt.expect_commit_success();
+    }
+
+    // Test the `buy_token` method, the tokens are deposited in the account of the buyer.
+    let receipt = buy_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        &issuance,
+        dec!(100),
+    );
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, issuance.token),
+        Some(dec!(100))
+    );
+
+    // Test the `toggle_transfer_freeze` method, so that holders may withdraw their tokens.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.freeze_admin, dec!(1))
+        .call_method(
+            issuance.component,
+            "toggle_transfer_freeze",
+            manifest_args!(false),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+
+    // A verified holder can not push tokens to an unverified account, even with their KYC badge
+    // in the auth zone and with the consent of the owner of the account.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.kyc_badge, dec!(1))
+        .withdraw_from_account(account_component, issuance.token, dec!(10))
+        .deposit_batch(unverified_account)
+        .build();
+    let receipt = test_runner
+        .execute_manifest_ignoring_fee(manifest, vec![badge.clone(), unverified_badge.clone()]);
+    receipt.expect_commit_failure();
+
+    // Test the `transfer` method, the KYC badge of the sender does not vouch for the unverified
+    // account.
+    let manifest = transfer(
+        &issuance,
+        account_component,
+        account_component,
+        unverified_account,
+    );
+    let receipt =
+        test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone(), unverified_badge]);
+    receipt.expect_commit_failure();
+
+    // Test the `transfer` method to the verified account, which presents its own KYC badge.
+    let manifest = transfer(
+        &issuance,
+        account_component,
+        verified_account,
+        verified_account,
+    );
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge, verified_badge]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(account_component, issuance.token),
+        Some(dec!(90))
+    );
+    assert_eq!(
+        test_runner.account_balance(verified_account, issuance.token),
+        Some(dec!(10))
+    );
+    assert_eq!(
+        test_runner.account_balance(unverified_account, issuance.token),
+        None
+    );
+}
+
+#[test]
+fn officers_recall_and_freeze_holdings() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the issuer, which is also the recall officer, the account of the
+    // freeze officer, the account of a holder and the account of an outsider with a badge of their
+    // own
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (freeze_officer_public_key, _freeze_officer_private_key, freeze_officer_account) =
+        test_runner.new_allocated_account();
+    let (holder_public_key, _holder_private_key, holder_account) =
+        test_runner.new_allocated_account();
+    let (outsider_public_key, _outsider_private_key, outsider_account) =
+        test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+    let freeze_officer_badge = NonFungibleGlobalId::from_public_key(&freeze_officer_public_key);
+    let holder_badge = NonFungibleGlobalId::from_public_key(&holder_public_key);
+    let outsider_badge = NonFungibleGlobalId::from_public_key(&outsider_public_key);
+    let fake_officer_badge = test_runner.create_non_fungible_resource(outsider_account);
+
+    let issuance = instantiate_regulated_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        vec![stage(dec!(1)), final_stage()],
+    );
+    for investor_account in [account_component, holder_account] {
+        let receipt = issue_kyc_badge(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            investor_account,
+        );
+        receipt.expect_commit_success();
+    }
+    let receipt = buy_token(
+        &mut test_runner,
+        &holder_public_key,
+        holder_account,
+        &issuance,
+        dec!(100),
+    );
+    receipt.expect_commit_success();
+    let vault_id = InternalAddress::new_or_panic(
+        test_runner.get_component_vaults(holder_account, issuance.token)[0].0,
+    );
+
+    // Test the `appoint_officer` method, with the issuer appointed to recall and another account
+    // appointed to freeze.
+    for (officer_account, duty) in [
+        (account_component, OfficerDuty::Recall),
+        (freeze_officer_account, OfficerDuty::Freeze),
+    ] {
+        let manifest = ManifestBuilder::new()
+            .create_proof_from_account_of_amount(account_component, issuance.general_admin, dec!(1))
+            .call_method(
+                issuance.component,
+                "appoint_officer",
+                manifest_args!("Officer".to_string(), duty),
+            )
+            .deposit_batch(officer_account)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![badge.clone(), freeze_officer_badge.clone()],
+        );
+        receipt.expect_commit_success();
+    }
+
+    // Test the `appoint_officer` method without the general admin badge.
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            issuance.component,
+            "appoint_officer",
+            manifest_args!("Outsider".to_string(), OfficerDuty::Recall),
+        )
+        .deposit_batch(outsider_account)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![outsider_badge.clone()]);
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+        )
+    });
+
+    // Test the `recall_from`, `freeze_vault` and `unfreeze_vault` methods with a badge which is
+    // not an officer badge, and with the officer badge of the other duty.
+    let recall = |officer_account: ComponentAddress, officer_badge: ResourceAddress| {
+        present_badge(officer_account, officer_badge)
+            .call_method_with_name_lookup(issuance.component, "recall_from", |lookup| {
+                (
+                    vault_id,
+                    dec!(30),
+                    "Court order".to_string(),
+                    lookup.proof("officer_badge"),
+                )
+            })
+            .deposit_batch(officer_account)
+            .build()
+    };
+    let freeze =
+        |method: &str, officer_account: ComponentAddress, officer_badge: ResourceAddress| {
+            present_badge(officer_account, officer_badge)
+                .call_method_with_name_lookup(issuance.component, method, |lookup| {
+                    (
+                        vault_id,
+                        "Court order".to_string(),
+                        lookup.proof("officer_badge"),
+                    )
+                })
+                .build()
+        };
+    let mut fake_badge_receipts = vec![test_runner.execute_manifest_ignoring_fee(
+        recall(outsider_account, fake_officer_badge),
+        vec![outsider_badge.clone()],
+    )];
+    let mut wrong_duty_receipts = vec![test_runner.execute_manifest_ignoring_fee(
+        recall(freeze_officer_account, issuance.officer_badge),
+        vec![freeze_officer_badge.clone()],
+    )];
+    for method in ["freeze_vault", "unfreeze_vault"] {
+        fake_badge_receipts.push(test_runner.execute_manifest_ignoring_fee(
+            freeze(method, outsider_account, fake_officer_badge),
+            vec![outsider_badge.clone()],
+        ));
+        wrong_duty_receipts.push(test_runner.execute_manifest_ignoring_fee(
+            freeze(method, account_component, issuance.officer_badge),
+            vec![badge.clone()],
+        ));
+    }
+    for receipt in fake_badge_receipts {
+        receipt.expect_specific_failure(|error| {
+            matches!(
+                error,
+                RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                    if message.contains("Invalid proof")
+            )
+        });
+    }
+    for receipt in wrong_duty_receipts {
+        receipt.expect_specific_failure(|error| {
+            matches!(
+                error,
+                RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                    if message.contains("not appointed to this duty")
+            )
+        });
+    }
+
+    // Test the `recall_from` method with the officer badge, the recalled tokens are sent to the
+    // issuer.
+    let manifest = present_badge(account_component, issuance.officer_badge)
+        .call_method_with_name_lookup(issuance.component, "recall_from", |lookup| {
+            (
+                vault_id,
+                dec!(30),
+                "Court order".to_string(),
+                lookup.proof("officer_badge"),
+            )
+        })
+        .take_all_from_worktop(issuance.token, "recalled")
+        .create_proof_from_account_of_amount(account_component, issuance.kyc_badge, dec!(1))
+        .pop_from_auth_zone("kyc_badge")
+        .call_method_with_name_lookup(issuance.component, "transfer", |lookup| {
+            (
+                lookup.bucket("recalled"),
+                account_component,
+                lookup.proof("kyc_badge"),
+            )
+        })
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+    let event: VaultRecalledEvent = find_event(&test_runner, &receipt, "VaultRecalledEvent");
+    assert_eq!(event.vault_id, vault_id);
+    assert_eq!(event.amount, dec!(30));
+    assert_eq!(event.reason, "Court order");
+    assert_eq!(
+        event.acting_badge.resource_address(),
+        issuance.officer_badge
+    );
+    assert_eq!(
+        test_runner.account_balance(holder_account, issuance.token),
+        Some(dec!(70))
+    );
+    assert_eq!(
+        test_runner.account_balance(account_component, issuance.token),
+        Some(dec!(30))
+    );
+
+    // Test the `toggle_transfer_freeze` method, so that holders may withdraw their tokens.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.freeze_admin, dec!(1))
+        .call_method(
+            issuance.component,
+            "toggle_transfer_freeze",
+            manifest_args!(false),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_commit_success();
+
+    // Test the `freeze_vault` and `unfreeze_vault` methods with the freeze officer badge, the
+    // holder can only send their tokens while their vault is not frozen.
+    for (method, succeeds) in [("freeze_vault", false), ("unfreeze_vault", true)] {
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            freeze(method, freeze_officer_account, issuance.officer_badge),
+            vec![freeze_officer_badge.clone()],
+        );
+        receipt.expect_commit_success();
+
+        let manifest = transfer(
+            &issuance,
+            holder_account,
+            account_component,
+            account_component,
+        );
+        let receipt = test_runner
+            .execute_manifest_ignoring_fee(manifest, vec![badge.clone(), holder_badge.clone()]);
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+    assert_eq!(
+        test_runner.account_balance(holder_account, issuance.token),
+        Some(dec!(60))
+    );
+}
+
+#[test]
+fn stages_are_walked_through_in_order() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the issuer, which is also an investor, and the account of someone who
+    // was never verified
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (unverified_public_key, _unverified_private_key, unverified_account) =
+        test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    // The initial supply is sold in the first stage, up to 50 more may be minted in the second.
+    let issuance = instantiate_regulated_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        vec![
+            StageDefinition {
+                mintable: false,
+                ..stage(dec!(1))
+            },
+            StageDefinition {
+                max_supply: Some(dec!(150)),
+                ..stage(dec!(2))
+            },
+            final_stage(),
+        ],
+    );
+    let receipt = issue_kyc_badge(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        &issuance,
+        account_component,
+    );
+    receipt.expect_commit_success();
+
+    // Test the `buy_token` method in the first stage, nothing may be minted beyond the supply.
+    for (quantity, succeeds) in [(dec!(100), true), (dec!(1), false)] {
+        let receipt = buy_token(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            quantity,
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+
+    // Test the `advance_stage` and `get_current_stage` methods.
+    let receipt = advance_stage(&mut test_runner, &public_key, account_component, &issuance);
+    receipt.expect_commit_success();
+    let manifest = ManifestBuilder::new()
+        .call_method(issuance.component, "get_current_stage", manifest_args!())
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    assert_eq!(receipt.expect_commit_success().output::<u8>(0), 2);
+
+    // Minting is enabled in the second stage, but only the component may mint.
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.general_admin, dec!(1))
+        .mint_fungible(issuance.token, dec!(10))
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge.clone()]);
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+        )
+    });
+
+    // Test the `buy_token` method in the second stage, at most 50 may be minted.
+    for (quantity, succeeds) in [(dec!(50), true), (dec!(1), false)] {
+        let receipt = buy_token(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            quantity,
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+    assert_eq!(
+        test_runner.account_balance(account_component, issuance.token),
+        Some(dec!(150))
+    );
+
+    // Test the `advance_stage` method to the final stage, past which there is nothing.
+    let receipt = advance_stage(&mut test_runner, &public_key, account_component, &issuance);
+    receipt.expect_commit_success();
+    let receipt = advance_stage(&mut test_runner, &public_key, account_component, &issuance);
+    receipt.expect_commit_failure();
+
+    // The token is no longer regulated, so it can be sent to anyone.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, issuance.token, dec!(10))
+        .deposit_batch(unverified_account)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![
+            badge,
+            NonFungibleGlobalId::from_public_key(&unverified_public_key),
+        ],
+    );
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(unverified_account, issuance.token),
+        Some(dec!(10))
+    );
+}
+
+#[test]
+fn invalid_stages_are_rejected() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, _account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Test the `instantiate_regulated_token` function with a free stage, and with a final stage
+    // which would leave minting, freezing and recalling on.
+    for stages in [
+        vec![stage(dec!(0)), final_stage()],
+        vec![stage(dec!(1)), stage(dec!(1))],
+    ] {
+        let manifest = ManifestBuilder::new()
+            .call_function(
+                package_address,
+                "RegulatedToken",
+                "instantiate_regulated_token",
+                manifest_args!(dec!(100), stages),
+            )
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        receipt.expect_commit_failure();
+    }
+}
+
+#[test]
+fn purchases_are_limited_by_the_stage() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the issuer, which is also the first buyer, and the account of a
+    // second buyer
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (buyer_public_key, _buyer_private_key, buyer_account) = test_runner.new_allocated_account();
+
+    // Up to 70 tokens are sold between epochs 10 and 20, at most 40 of them to a single buyer.
+    let issuance = instantiate_regulated_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        vec![
+            StageDefinition {
+                sale_cap: Some(dec!(70)),
+                purchase_limit: Some(dec!(40)),
+                sale_window: Some((Epoch::of(10), Epoch::of(20))),
+                ..stage(dec!(1))
+            },
+            final_stage(),
+        ],
+    );
+
+    // Test the `issue_kyc_badge` method, every account is issued a single badge.
+    for (investor_account, succeeds) in [
+        (account_component, true),
+        (buyer_account, true),
+        (account_component, false),
+    ] {
+        let receipt = issue_kyc_badge(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            investor_account,
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+
+    // Test the `buy_token` method before, during and after the sale window.
+    let purchases = [
+        // The sale has not started yet
+        (5, &public_key, account_component, dec!(10), false),
+        (10, &public_key, account_component, dec!(40), true),
+        // The first buyer has reached the purchase limit
+        (10, &public_key, account_component, dec!(1), false),
+        // 71 tokens would exceed the sale cap
+        (10, &buyer_public_key, buyer_account, dec!(31), false),
+        (19, &buyer_public_key, buyer_account, dec!(20), true),
+        // The sale has ended
+        (20, &buyer_public_key, buyer_account, dec!(10), false),
+    ];
+    for (epoch, signer_public_key, account, quantity, succeeds) in purchases {
+        test_runner.set_current_epoch(Epoch::of(epoch));
+        let receipt = buy_token(
+            &mut test_runner,
+            signer_public_key,
+            account,
+            &issuance,
+            quantity,
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+    assert_eq!(
+        test_runner.account_balance(account_component, issuance.token),
+        Some(dec!(40))
+    );
+    assert_eq!(
+        test_runner.account_balance(buyer_account, issuance.token),
+        Some(dec!(20))
+    );
+}
+
+#[test]
+fn kyc_badge_can_be_reissued_once_burned() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create the account of the issuer and the account of an investor
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+    let (_investor_public_key, _investor_private_key, investor_account) =
+        test_runner.new_allocated_account();
+    let badge = NonFungibleGlobalId::from_public_key(&public_key);
+
+    let issuance = instantiate_regulated_token(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        vec![stage(dec!(1)), final_stage()],
+    );
+
+    // Test the `issue_kyc_badge` method, a second badge is refused while the first one exists.
+    for succeeds in [true, false] {
+        let receipt = issue_kyc_badge(
+            &mut test_runner,
+            &public_key,
+            account_component,
+            &issuance,
+            investor_account,
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_commit_failure();
+        }
+    }
+
+    // The compliance admin revokes the badge by recalling and burning it.
+    let vault_id = InternalAddress::new_or_panic(
+        test_runner.get_component_vaults(investor_account, issuance.kyc_badge)[0].0,
+    );
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.compliance_admin, dec!(1))
+        .recall(vault_id, dec!(1))
+        .burn_all_from_worktop(issuance.kyc_badge)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(manifest, vec![badge]);
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(investor_account, issuance.kyc_badge),
+        Some(dec!(0))
+    );
+
+    // Test the `issue_kyc_badge` method once the first badge is gone.
+    let receipt = issue_kyc_badge(
+        &mut test_runner,
+        &public_key,
+        account_component,
+        &issuance,
+        investor_account,
+    );
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_runner.account_balance(investor_account, issuance.kyc_badge),
+        Some(dec!(1))
+    );
+}
+
+/// The addresses of a regulated token component, of the resources it created and of the admin
+/// badges held by the issuer.
+struct Issuance {
+    component: ComponentAddress,
+    token: ResourceAddress,
+    kyc_badge: ResourceAddress,
+    officer_badge: ResourceAddress,
+    general_admin: ResourceAddress,
+    freeze_admin: ResourceAddress,
+    compliance_admin: ResourceAddress,
+}
+
+/// A stage selling at the given price, in which every behavior of the token is enabled and
+/// nothing is limited.
+fn stage(price: Decimal) -> StageDefinition {
+    StageDefinition {
+        price,
+        mintable: true,
+        freezable: true,
+        recallable: true,
+        max_supply: None,
+        description: format!("Selling at {}", price),
+        sale_cap: None,
+        purchase_limit: None,
+        sale_window: None,
+    }
+}
+
+/// The final stage, in which the token is no longer regulated.
+fn final_stage() -> StageDefinition {
+    StageDefinition {
+        mintable: false,
+        freezable: false,
+        recallable: false,
+        description: "Unregulated".to_string(),
+        ..stage(dec!(1))
+    }
+}
+
+/// Instantiates a regulated token with an initial supply of 100 and the given stages, and deposits
+/// the admin badges in the account of the issuer.
+fn instantiate_regulated_token(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    stages: Vec<StageDefinition>,
+) -> Issuance {
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Test the `instantiate_regulated_token` function.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "RegulatedToken",
+            "instantiate_regulated_token",
+            manifest_args!(dec!(100), stages),
+        )
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    );
+    let commit = receipt.expect_commit_success();
+    let resources = commit.new_resource_addresses();
+
+    Issuance {
+        component: commit.new_component_addresses()[0],
+        general_admin: resources[0],
+        freeze_admin: resources[1],
+        compliance_admin: resources[2],
+        kyc_badge: resources[3],
+        officer_badge: resources[4],
+        token: resources[5],
+    }
+}
+
+/// Issues a KYC badge to the investor account with the compliance admin badge held in the account
+/// of the issuer.
+fn issue_kyc_badge(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    issuance: &Issuance,
+    investor_account: ComponentAddress,
+) -> TransactionReceipt {
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.compliance_admin, dec!(1))
+        .call_method(
+            issuance.component,
+            "issue_kyc_badge",
+            manifest_args!("Investor".to_string(), investor_account),
+        )
+        .build();
+    test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    )
+}
+
+/// Advances the regulated token to its next stage with the general admin badge held in the account
+/// of the issuer.
+fn advance_stage(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    issuance: &Issuance,
+) -> TransactionReceipt {
+    let manifest = ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, issuance.general_admin, dec!(1))
+        .call_method(issuance.component, "advance_stage", manifest_args!())
+        .build();
+    test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    )
+}
+
+/// Buys the given quantity of tokens into the account which the public key is the owner of, paying
+/// with 1000 XRD of which the change is returned.
+fn buy_token(
+    test_runner: &mut DefaultTestRunner,
+    public_key: &Secp256k1PublicKey,
+    account_component: ComponentAddress,
+    issuance: &Issuance,
+    quantity: Decimal,
+) -> TransactionReceipt {
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, XRD, dec!(1000))
+        .take_all_from_worktop(XRD, "payment")
+        .create_proof_from_account_of_amount(account_component, issuance.kyc_badge, dec!(1))
+        .pop_from_auth_zone("kyc_badge")
+        .call_method_with_name_lookup(issuance.component, "buy_token", |lookup| {
+            (
+                quantity,
+                lookup.bucket("payment"),
+                account_component,
+                lookup.proof("kyc_badge"),
+            )
+        })
+        .deposit_batch(account_component)
+        .build();
+    test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(public_key)],
+    )
+}
+
+/// Builds a manifest which sends 10 tokens from the sender to the recipient, with a proof of the
+/// KYC badge held by the presenting account.
+fn transfer(
+    issuance: &Issuance,
+    sender: ComponentAddress,
+    presenting_account: ComponentAddress,
+    recipient: ComponentAddress,
+) -> TransactionManifestV1 {
+    ManifestBuilder::new()
+        .withdraw_from_account(sender, issuance.token, dec!(10))
+        .take_all_from_worktop(issuance.token, "tokens")
+        .create_proof_from_account_of_amount(presenting_account, issuance.kyc_badge, dec!(1))
+        .pop_from_auth_zone("kyc_badge")
+        .call_method_with_name_lookup(issuance.component, "transfer", |lookup| {
+            (
+                lookup.bucket("tokens"),
+                recipient,
+                lookup.proof("kyc_badge"),
+            )
+        })
+        .build()
+}
+
+/// Starts a manifest which presents a badge of the given resource held in the account as the
+/// "officer_badge" proof that officer methods take.
+fn present_badge(account_component: ComponentAddress, badge: ResourceAddress) -> ManifestBuilder {
+    ManifestBuilder::new()
+        .create_proof_from_account_of_amount(account_component, badge, dec!(1))
+        .pop_from_auth_zone("officer_badge")
+}
+
+/// Decodes the event of the given name emitted by the transaction, failing if there is none.
+fn find_event<T: ScryptoDecode>(
+    test_runner: &DefaultTestRunner,
+    receipt: &TransactionReceipt,
+    name: &str,
+) -> T {
+    let (_, data) = receipt
+        .expect_commit_success()
+        .application_events
+        .iter()
+        .find(|(event_type_identifier, _)| test_runner.event_name(event_type_identifier) == name)
+        .expect("Event was not emitted");
+
+    scrypto_decode(data).unwrap()
+}
@@ -0,0 +1,320 @@
+use scrypto::prelude::*;
+
+/// Logarithm, exponential and power functions over decimals used by the weighted pool math.
+mod math;
+
+/// The number of basis points in one whole, used to convert the fee from basis points into a
+/// fraction.
+const BASIS_POINTS: u16 = 10_000;
+
+/// The highest fee that a weighted pool may charge on swaps, 10%.
+const MAX_FEE_BPS: u16 = 1_000;
+
+/// The smallest and largest number of resources a weighted pool may hold.
+const MIN_RESOURCES: usize = 2;
+const MAX_RESOURCES: usize = 8;
+
+/// The smallest and largest normalized weight of a resource, 1% and 99%, in basis points. The
+/// ratio of two weights is the exponent of the swap math, and keeping it bounded keeps the power
+/// approximation accurate.
+const MIN_WEIGHT_BPS: u16 = 100;
+const MAX_WEIGHT_BPS: u16 = 9_900;
+
+/// The largest input of a swap, 30% of the reserves of the input resource, in basis points. Like
+/// the max-in ratio of Balancer, this keeps the power of the swap math within the range where it
+/// can be computed, whatever the ratio of the weights.
+const MAX_IN_RATIO_BPS: u16 = 3_000;
+
+#[blueprint]
+mod weighted_pool {
+    /// A WeightedPool is a Balancer-style pool of between two and eight resources where each
+    /// resource is given a weight. Swaps keep the weighted product of the reserves constant, so
+    /// the value held in each resource always makes up its weight of the value of the pool, much
+    /// like an index fund which rebalances itself through trades.
+    ///
+    /// The resources themselves are held by a native `MultiResourcePool`, which mints and redeems
+    /// the pool units. This blueprint only adds the pricing on top of it.
+    struct WeightedPool {
+        pool_component: Global<MultiResourcePool>,
+
+        /// The normalized weight of each resource of the pool. The weights always add up to one.
+        weights: IndexMap<ResourceAddress, Decimal>,
+
+        /// The fee charged on the input of every swap, in basis points. The fee stays in the pool
+        /// and thus increases the redemption value of the pool units.
+        fee_bps: u16,
+    }
+
+    impl WeightedPool {
+        /// Instantiates a new weighted pool from a list of resources and their weights. The
+        /// weights do not need to add up to one, they are normalized by their sum, so weights of
+        /// `[2, 1, 1]` give the same pool as `[0.5, 0.25, 0.25]`.
+        pub fn new(
+            owner_role: OwnerRole,
+            weights: Vec<(ResourceAddress, Decimal)>,
+            fee_bps: u16,
+        ) -> Global<WeightedPool> {
+            assert!(
+                weights.len() >= MIN_RESOURCES && weights.len() <= MAX_RESOURCES,
+                "A weighted pool must hold between {} and {} resources",
+                MIN_RESOURCES,
+                MAX_RESOURCES
+            );
+            assert!(
+                weights.iter().all(|(_, weight)| *weight > Decimal::ZERO),
+                "Weights must be positive"
+            );
+            // The weights are collected into a map below, which would silently drop a resource
+            // given twice and leave the normalized weights adding up to less than one.
+            let resource_addresses: HashSet<ResourceAddress> = weights
+                .iter()
+                .map(|(resource_address, _)| *resource_address)
+                .collect();
+            assert_eq!(
+                resource_addresses.len(),
+                weights.len(),
+                "A resource must not be given more than once"
+            );
+            assert!(
+                fee_bps <= MAX_FEE_BPS,
+                "Fee must not exceed {} basis points",
+                MAX_FEE_BPS
+            );
+
+            let total_weight = weights.iter().fold(Decimal::ZERO, |total, (_, weight)| {
+                total.checked_add(*weight).unwrap()
+            });
+            let weights: IndexMap<ResourceAddress, Decimal> = weights
+                .into_iter()
+                .map(|(resource_address, weight)| {
+                    (resource_address, weight.checked_div(total_weight).unwrap())
+                })
+                .collect();
+            let min_weight = Decimal::from(MIN_WEIGHT_BPS)
+                .checked_div(BASIS_POINTS)
+                .unwrap();
+            let max_weight = Decimal::from(MAX_WEIGHT_BPS)
+                .checked_div(BASIS_POINTS)
+                .unwrap();
+            assert!(
+                weights
+                    .values()
+                    .all(|weight| *weight >= min_weight && *weight <= max_weight),
+                "Normalized weights must be between {} and {} basis points",
+                MIN_WEIGHT_BPS,
+                MAX_WEIGHT_BPS
+            );
+
+            let (address_reservation, component_address) =
+                Runtime::allocate_component_address(WeightedPool::blueprint_id());
+            let global_component_caller_badge =
+                NonFungibleGlobalId::global_caller_badge(component_address);
+
+            // Creating a new pool will check for us that none of the resources are non-fungible.
+            let pool_component = Blueprint::<MultiResourcePool>::instantiate(
+                owner_role.clone(),
+                rule!(require(global_component_caller_badge)),
+                weights.keys().cloned().collect(),
+                None,
+            );
+
+            Self {
+                pool_component,
+                weights,
+                fee_bps,
+            }
+            .instantiate()
+            .prepare_to_globalize(owner_role)
+            .with_address(address_reservation)
+            .globalize()
+        }
+
+        /// Contributes a bucket of each resource of the pool and returns the pool units along with
+        /// whatever could not be contributed in the current ratio of the reserves.
+        pub fn add_liquidity(&mut self, buckets: Vec<Bucket>) -> (Bucket, Vec<Bucket>) {
+            self.pool_component.contribute(buckets)
+        }
+
+        /// Joins the pool with a single resource. The input is split according to the weights,
+        /// the parts belonging to the other resources are swapped into them and everything is then
+        /// contributed. This costs the swap fee and price impact on the swapped parts, just as
+        /// doing the swaps by hand would.
+        pub fn add_liquidity_single(&mut self, mut input_bucket: Bucket) -> (Bucket, Vec<Bucket>) {
+            let input_resource = input_bucket.resource_address();
+            let input_amount = input_bucket.amount();
+            assert!(
+                self.weights.contains_key(&input_resource),
+                "Resource does not belong to the pool"
+            );
+            // There is nothing to swap the input against until the pool has been funded.
+            assert!(
+                self.pool_component
+                    .get_vault_amounts()
+                    .values()
+                    .all(|reserves| *reserves > Decimal::ZERO),
+                "The pool must hold liquidity before it can be joined with a single resource"
+            );
+
+            let mut buckets = Vec::new();
+            for (resource_address, weight) in self.weights.clone() {
+                if resource_address == input_resource {
+                    continue;
+                }
+                let part = input_bucket.take_advanced(
+                    input_amount.checked_mul(weight).unwrap(),
+                    WithdrawStrategy::Rounded(RoundingMode::ToZero),
+                );
+                buckets.push(self.swap(part, resource_address));
+            }
+            buckets.push(input_bucket);
+
+            self.pool_component.contribute(buckets)
+        }
+
+        pub fn remove_liquidity(&mut self, pool_units: Bucket) -> Vec<Bucket> {
+            self.pool_component.redeem(pool_units)
+        }
+
+        /// Exits the pool into a single resource. The pool units are redeemed and every redeemed
+        /// resource other than `output_resource` is swapped into it.
+        pub fn remove_liquidity_single(
+            &mut self,
+            pool_units: Bucket,
+            output_resource: ResourceAddress,
+        ) -> Bucket {
+            assert!(
+                self.weights.contains_key(&output_resource),
+                "Resource does not belong to the pool"
+            );
+
+            let (mut output_buckets, other_buckets): (Vec<Bucket>, Vec<Bucket>) = self
+                .pool_component
+                .redeem(pool_units)
+                .into_iter()
+                .partition(|bucket| bucket.resource_address() == output_resource);
+
+            let mut output_bucket = output_buckets.pop().unwrap();
+            for bucket in other_buckets {
+                output_bucket.put(self.swap(bucket, output_resource));
+            }
+            output_bucket
+        }
+
+        /// Swaps the input bucket for `output_resource`, which may be any other resource of the
+        /// pool.
+        pub fn swap(&mut self, input_bucket: Bucket, output_resource: ResourceAddress) -> Bucket {
+            let output_amount = self.get_amount_out(
+                input_bucket.resource_address(),
+                output_resource,
+                input_bucket.amount(),
+            );
+
+            // NOTE: It's the responsibility of the user of the pool to do the appropriate rounding
+            // before calling the withdraw method.
+
+            self.pool_component.protected_deposit(input_bucket);
+            self.pool_component.protected_withdraw(
+                output_resource,
+                output_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToZero),
+            )
+        }
+
+        /// Returns the amount of `output_resource` that `swap` would currently give for
+        /// `input_amount` of `input_resource`:
+        ///
+        /// `output_reserves * (1 - (input_reserves / (input_reserves + input_amount)) ^ (input_weight / output_weight))`
+        ///
+        /// where the input amount is taken net of the fee. Inputs above 30% of the input reserves
+        /// are rejected.
+        pub fn get_amount_out(
+            &self,
+            input_resource: ResourceAddress,
+            output_resource: ResourceAddress,
+            input_amount: Decimal,
+        ) -> Decimal {
+            assert_ne!(
+                input_resource, output_resource,
+                "Input and output resources must be different"
+            );
+            let (input_reserves, input_weight) = self.reserves_and_weight(input_resource);
+            let (output_reserves, output_weight) = self.reserves_and_weight(output_resource);
+            let max_input = input_reserves
+                .checked_mul(MAX_IN_RATIO_BPS)
+                .unwrap()
+                .checked_div(BASIS_POINTS)
+                .unwrap();
+            assert!(
+                input_amount <= max_input,
+                "Input must not exceed {} basis points of the reserves of the input resource",
+                MAX_IN_RATIO_BPS
+            );
+
+            let input_amount_after_fee = input_amount
+                .checked_mul(Decimal::ONE.checked_sub(self.fee_fraction()).unwrap())
+                .unwrap();
+            let reserves_ratio = input_reserves
+                .checked_div(input_reserves.checked_add(input_amount_after_fee).unwrap())
+                .unwrap();
+            let weights_ratio = input_weight.checked_div(output_weight).unwrap();
+
+            // The power is an approximation, so it is rounded up to make sure that the pool never
+            // gives out too much. It may then come out at or above one for tiny inputs, in which
+            // case there is nothing to give out.
+            let retained_fraction = math::pow_up(reserves_ratio, weights_ratio);
+            if retained_fraction >= Decimal::ONE {
+                return Decimal::ZERO;
+            }
+
+            output_reserves
+                .checked_mul(Decimal::ONE.checked_sub(retained_fraction).unwrap())
+                .unwrap()
+        }
+
+        /// Returns the marginal price of `base_resource` expressed in `quote_resource`, ignoring
+        /// the fee.
+        pub fn spot_price(
+            &self,
+            base_resource: ResourceAddress,
+            quote_resource: ResourceAddress,
+        ) -> Decimal {
+            let (base_reserves, base_weight) = self.reserves_and_weight(base_resource);
+            let (quote_reserves, quote_weight) = self.reserves_and_weight(quote_resource);
+
+            quote_reserves
+                .checked_div(quote_weight)
+                .unwrap()
+                .checked_div(base_reserves.checked_div(base_weight).unwrap())
+                .unwrap()
+        }
+
+        pub fn get_weights(&self) -> IndexMap<ResourceAddress, Decimal> {
+            self.weights.clone()
+        }
+
+        /// Returns the fee charged on swaps in basis points.
+        pub fn get_fee(&self) -> u16 {
+            self.fee_bps
+        }
+
+        fn reserves_and_weight(&self, resource_address: ResourceAddress) -> (Decimal, Decimal) {
+            let weight = *self
+                .weights
+                .get(&resource_address)
+                .expect("Resource does not belong to the pool");
+            let reserves = *self
+                .pool_component
+                .get_vault_amounts()
+                .get(&resource_address)
+                .unwrap();
+
+            (reserves, weight)
+        }
+
+        fn fee_fraction(&self) -> Decimal {
+            Decimal::from(self.fee_bps)
+                .checked_div(BASIS_POINTS)
+                .unwrap()
+        }
+    }
+}
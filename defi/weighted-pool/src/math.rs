@@ -0,0 +1,121 @@
+//! Decimal versions of the logarithm and exponential functions, which the weighted pool needs to
+//! raise reserve ratios to the power of weight ratios.
+
+use scrypto::prelude::*;
+
+/// The number of series terms after which the logarithm and exponential give up converging. With
+/// the range reductions below both converge in far fewer terms than this.
+const MAX_TERMS: u64 = 64;
+
+fn ln_2() -> Decimal {
+    dec!("0.693147180559945309")
+}
+
+/// A bound on the relative error of `pow`, well above what the series and the rounding of each
+/// decimal operation add up to for the bases and exponents of a weighted pool.
+fn max_pow_relative_error() -> Decimal {
+    dec!("0.00000000000001")
+}
+
+/// Computes `base` raised to the power of `exponent` like `pow`, rounded up so that the result is
+/// never below the exact power.
+pub fn pow_up(base: Decimal, exponent: Decimal) -> Decimal {
+    let raw = pow(base, exponent);
+
+    raw.checked_add(raw.checked_mul(max_pow_relative_error()).unwrap())
+        .unwrap()
+        .checked_add(dec!("0.000000000000000001"))
+        .unwrap()
+}
+
+/// Computes `base` raised to the power of `exponent`, for a positive `base`.
+pub fn pow(base: Decimal, exponent: Decimal) -> Decimal {
+    assert!(base > Decimal::ZERO, "Base must be positive");
+    if exponent.is_zero() || base == Decimal::ONE {
+        return Decimal::ONE;
+    }
+
+    exp(exponent.checked_mul(ln(base)).unwrap())
+}
+
+/// Computes the natural logarithm of a positive `x`.
+///
+/// `x` is first reduced to `m * 2^k` with `m` in `[1, 2)`, and then `ln(m)` is computed through
+/// the series `2 * (z + z^3 / 3 + z^5 / 5 + ...)` with `z = (m - 1) / (m + 1)`, which is smaller
+/// than a third and thus converges quickly.
+pub fn ln(x: Decimal) -> Decimal {
+    assert!(
+        x > Decimal::ZERO,
+        "Logarithm is only defined for positive numbers"
+    );
+
+    let two = Decimal::from(2);
+    let mut m = x;
+    let mut k: i64 = 0;
+    while m >= two {
+        m = m.checked_div(2).unwrap();
+        k += 1;
+    }
+    while m < Decimal::ONE {
+        m = m.checked_mul(2).unwrap();
+        k -= 1;
+    }
+
+    let z = m
+        .checked_sub(Decimal::ONE)
+        .unwrap()
+        .checked_div(m.checked_add(Decimal::ONE).unwrap())
+        .unwrap();
+    let z_squared = z.checked_mul(z).unwrap();
+
+    let mut term = z;
+    let mut sum = Decimal::ZERO;
+    for n in 0..MAX_TERMS {
+        if term.is_zero() {
+            break;
+        }
+        sum = sum
+            .checked_add(term.checked_div(2 * n + 1).unwrap())
+            .unwrap();
+        term = term.checked_mul(z_squared).unwrap();
+    }
+
+    sum.checked_mul(2)
+        .unwrap()
+        .checked_add(ln_2().checked_mul(k).unwrap())
+        .unwrap()
+}
+
+/// Computes `e` raised to the power of `y`.
+///
+/// `y` is first reduced to `r + k * ln(2)` with `r` in `[0, ln(2))`, so that the Taylor series of
+/// `e^r` converges quickly, and the result is then multiplied by `2^k`.
+pub fn exp(y: Decimal) -> Decimal {
+    if y < Decimal::ZERO {
+        return Decimal::ONE
+            .checked_div(exp(Decimal::ZERO.checked_sub(y).unwrap()))
+            .unwrap();
+    }
+
+    let mut r = y;
+    let mut k: u64 = 0;
+    while r >= ln_2() {
+        r = r.checked_sub(ln_2()).unwrap();
+        k += 1;
+    }
+
+    let mut term = Decimal::ONE;
+    let mut sum = Decimal::ONE;
+    for n in 1..MAX_TERMS {
+        term = term.checked_mul(r).unwrap().checked_div(n).unwrap();
+        if term.is_zero() {
+            break;
+        }
+        sum = sum.checked_add(term).unwrap();
+    }
+
+    for _ in 0..k {
+        sum = sum.checked_mul(2).unwrap();
+    }
+    sum
+}
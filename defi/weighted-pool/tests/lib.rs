@@ -0,0 +1,318 @@
+use radix_engine::errors::{ApplicationError, RuntimeError};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+#[test]
+fn test_weighted_pool() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    // Create the three resources of the pool
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_c = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `new` function with a 50/25/25 pool.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "WeightedPool",
+            "new",
+            manifest_args!(
+                OwnerRole::None,
+                vec![
+                    (resource_a, dec!(2)),
+                    (resource_b, dec!(1)),
+                    (resource_c, dec!(1))
+                ],
+                0u16
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    let commit_success = receipt.expect_commit_success();
+    let weighted_pool = commit_success.new_component_addresses()[0];
+    let pool_units = commit_success.new_resource_addresses()[0];
+
+    // Test the `add_liquidity` method. With these weights, equal reserves of B and C and twice as
+    // much of A price every resource at one.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(200))
+        .withdraw_from_account(account_component, resource_b, dec!(100))
+        .withdraw_from_account(account_component, resource_c, dec!(100))
+        .take_all_from_worktop(resource_a, "resource_a")
+        .take_all_from_worktop(resource_b, "resource_b")
+        .take_all_from_worktop(resource_c, "resource_c")
+        .call_method_with_name_lookup(weighted_pool, "add_liquidity", |lookup| {
+            (vec![
+                lookup.bucket("resource_a"),
+                lookup.bucket("resource_b"),
+                lookup.bucket("resource_c"),
+            ],)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // Test the `swap` method from A to C.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(weighted_pool, "swap", |lookup| {
+            (lookup.bucket("input"), resource_c)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // 100 * (1 - (200 / 210) ^ 2) is a little over 9.29 C.
+    let balance_c = test_runner
+        .account_balance(account_component, resource_c)
+        .unwrap();
+    assert!(balance_c > dec!("909.29") && balance_c < dec!("909.30"));
+
+    // Test the `add_liquidity_single` method with B only.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_b, dec!(40))
+        .take_all_from_worktop(resource_b, "input")
+        .call_method_with_name_lookup(weighted_pool, "add_liquidity_single", |lookup| {
+            (lookup.bucket("input"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // Test the `remove_liquidity_single` method, redeeming a tenth of the pool units into A only.
+    let balance_a = test_runner
+        .account_balance(account_component, resource_a)
+        .unwrap();
+    let balance_b = test_runner.account_balance(account_component, resource_b);
+    let pool_units_amount = test_runner
+        .account_balance(account_component, pool_units)
+        .unwrap()
+        .checked_div(10)
+        .unwrap();
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, pool_units, pool_units_amount)
+        .take_all_from_worktop(pool_units, "pool_units")
+        .call_method_with_name_lookup(weighted_pool, "remove_liquidity_single", |lookup| {
+            (lookup.bucket("pool_units"), resource_a)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("{:?}\n", receipt);
+    receipt.expect_commit_success();
+
+    // A tenth of the pool is worth about 41.3 A at the spot prices, of which swapping the B and C
+    // into A loses some to price impact.
+    let received_a = test_runner
+        .account_balance(account_component, resource_a)
+        .unwrap()
+        .checked_sub(balance_a)
+        .unwrap();
+    assert!(received_a > dec!(35) && received_a < dec!("41.3"));
+    assert_eq!(
+        test_runner.account_balance(account_component, resource_b),
+        balance_b
+    );
+}
+
+#[test]
+fn test_weighted_pool_rejects_a_single_resource() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `new` function with a single resource.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "WeightedPool",
+            "new",
+            manifest_args!(OwnerRole::None, vec![(resource_a, dec!(1))], 0u16),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn test_weighted_pool_rejects_invalid_weights() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `new` function with a resource given twice, and with a weight below 1%.
+    for weights in [
+        vec![
+            (resource_a, dec!(1)),
+            (resource_b, dec!(1)),
+            (resource_a, dec!(1)),
+        ],
+        vec![(resource_a, dec!(1000)), (resource_b, dec!(1))],
+    ] {
+        let manifest = ManifestBuilder::new()
+            .call_function(
+                package_address,
+                "WeightedPool",
+                "new",
+                manifest_args!(OwnerRole::None, weights, 0u16),
+            )
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        receipt.expect_commit_failure();
+    }
+}
+
+#[test]
+fn test_weighted_pool_rejects_oversized_inputs() {
+    // Set up environment.
+    let mut test_runner = TestRunnerBuilder::new().build();
+
+    // Create an account
+    let (public_key, _private_key, account_component) = test_runner.new_allocated_account();
+
+    // Publish package
+    let package_address = test_runner.compile_and_publish(this_package!());
+
+    let resource_a = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+    let resource_b = test_runner.create_fungible_resource(dec!(1000), 18u8, account_component);
+
+    // Test the `new` function with a 1/99 pool.
+    let manifest = ManifestBuilder::new()
+        .call_function(
+            package_address,
+            "WeightedPool",
+            "new",
+            manifest_args!(
+                OwnerRole::None,
+                vec![(resource_a, dec!(99)), (resource_b, dec!(1))],
+                0u16
+            ),
+        )
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    let weighted_pool = receipt.expect_commit_success().new_component_addresses()[0];
+
+    // Test the `add_liquidity_single` method on the empty pool.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(10))
+        .take_all_from_worktop(resource_a, "input")
+        .call_method_with_name_lookup(weighted_pool, "add_liquidity_single", |lookup| {
+            (lookup.bucket("input"),)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                if message.contains("must hold liquidity")
+        )
+    });
+
+    // Test the `add_liquidity` method.
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(account_component, resource_a, dec!(99))
+        .withdraw_from_account(account_component, resource_b, dec!(1))
+        .take_all_from_worktop(resource_a, "resource_a")
+        .take_all_from_worktop(resource_b, "resource_b")
+        .call_method_with_name_lookup(weighted_pool, "add_liquidity", |lookup| {
+            (vec![
+                lookup.bucket("resource_a"),
+                lookup.bucket("resource_b"),
+            ],)
+        })
+        .deposit_batch(account_component)
+        .build();
+    let receipt = test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    receipt.expect_commit_success();
+
+    // Test the `swap` method from A to B with 30% of the reserves of A, and then with twice them.
+    for (amount, succeeds) in [(dec!("29.7"), true), (dec!(198), false)] {
+        let manifest = ManifestBuilder::new()
+            .withdraw_from_account(account_component, resource_a, amount)
+            .take_all_from_worktop(resource_a, "input")
+            .call_method_with_name_lookup(weighted_pool, "swap", |lookup| {
+                (lookup.bucket("input"), resource_b)
+            })
+            .deposit_batch(account_component)
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        if succeeds {
+            receipt.expect_commit_success();
+        } else {
+            receipt.expect_specific_failure(|error| {
+                matches!(
+                    error,
+                    RuntimeError::ApplicationError(ApplicationError::PanicMessage(message))
+                        if message.contains("Input must not exceed")
+                )
+            });
+        }
+    }
+}
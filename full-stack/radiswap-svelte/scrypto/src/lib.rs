@@ -49,15 +49,31 @@ mod radiswap {
             resource1: Bucket,
             resource2: Bucket,
         ) -> (Bucket, Option<Bucket>) {
-            Runtime::emit_event(AddLiquidityEvent([
+            let mut contributed_resources = [
                 (resource1.resource_address(), resource1.amount()),
                 (resource2.resource_address(), resource2.amount()),
-            ]));
+            ];
 
             // All the checks for correctness of buckets and everything else is handled by the pool
             // component! Just pass it the resources and it will either return the pool units back
             // if it succeeds or abort on failure.
-            self.pool_component.contribute((resource1, resource2))
+            let (pool_units, change) = self.pool_component.contribute((resource1, resource2));
+
+            // Whatever the pool did not need is given back and was not contributed.
+            if let Some(change) = &change {
+                for (resource_address, amount) in contributed_resources.iter_mut() {
+                    if *resource_address == change.resource_address() {
+                        *amount = amount.checked_sub(change.amount()).unwrap();
+                    }
+                }
+            }
+
+            Runtime::emit_event(AddLiquidityEvent {
+                contributed_resources,
+                pool_units_amount: pool_units.amount(),
+            });
+
+            (pool_units, change)
         }
 
         /// This method does not need to be here - the pool units are redeemable without it by the
@@ -80,24 +96,19 @@ mod radiswap {
         }
 
         pub fn swap(&mut self, input_bucket: Bucket) -> Bucket {
-            let mut reserves = self.vault_reserves();
-
             let input_amount = input_bucket.amount();
 
-            let input_reserves = reserves
-                .remove(&input_bucket.resource_address())
-                .expect("Resource does not belong to the pool");
-            let (output_resource_address, output_reserves) = reserves.into_iter().next().unwrap();
+            let (input_reserves, output_resource_address, output_reserves) =
+                self.reserves_for(input_bucket.resource_address());
 
-            let output_amount = input_amount
-                .checked_mul(output_reserves)
-                .unwrap()
-                .checked_div(input_reserves.checked_add(input_amount).unwrap())
-                .unwrap();
+            let output_amount =
+                Self::calculate_output_amount(input_amount, input_reserves, output_reserves);
 
             Runtime::emit_event(SwapEvent {
                 input: (input_bucket.resource_address(), input_bucket.amount()),
                 output: (output_resource_address, output_amount),
+                lp_fee: Decimal::ZERO,
+                protocol_fee: Decimal::ZERO,
             });
 
             // NOTE: It's the responsibility of the user of the pool to do the appropriate rounding
@@ -107,6 +118,185 @@ mod radiswap {
             self.withdraw(output_resource_address, output_amount)
         }
 
+        /// Swaps the whole input bucket, failing if the output would be less than `min_output` or
+        /// if the transaction is committed after `deadline_epoch`.
+        pub fn swap_exact_input(
+            &mut self,
+            input_bucket: Bucket,
+            min_output: Decimal,
+            deadline_epoch: Epoch,
+        ) -> Bucket {
+            Self::assert_deadline_not_passed(deadline_epoch);
+
+            let output_bucket = self.swap(input_bucket);
+            assert!(
+                output_bucket.amount() >= min_output,
+                "Output amount of {} is below the minimum output of {}",
+                output_bucket.amount(),
+                min_output
+            );
+
+            output_bucket
+        }
+
+        /// Swaps just enough of the input bucket to receive exactly `desired_output` of the other
+        /// resource, failing if the input bucket is not enough or if the transaction is committed
+        /// after `deadline_epoch`. The unused part of the input is returned along with the output.
+        pub fn swap_exact_output(
+            &mut self,
+            mut input_bucket: Bucket,
+            desired_output: Decimal,
+            deadline_epoch: Epoch,
+        ) -> (Bucket, Bucket) {
+            Self::assert_deadline_not_passed(deadline_epoch);
+            assert!(
+                desired_output > Decimal::ZERO,
+                "Desired output must be positive"
+            );
+
+            let (input_reserves, output_resource_address, output_reserves) =
+                self.reserves_for(input_bucket.resource_address());
+            let input_amount =
+                Self::calculate_input_amount(desired_output, input_reserves, output_reserves);
+            assert!(
+                input_amount <= input_bucket.amount(),
+                "Input of {} is not enough, {} is required for the desired output",
+                input_bucket.amount(),
+                input_amount
+            );
+
+            // The input is rounded up to the divisibility of its resource, in favor of the pool.
+            let input_used = input_bucket.take_advanced(
+                input_amount,
+                WithdrawStrategy::Rounded(RoundingMode::ToPositiveInfinity),
+            );
+
+            Runtime::emit_event(SwapEvent {
+                input: (input_used.resource_address(), input_used.amount()),
+                output: (output_resource_address, desired_output),
+                lp_fee: Decimal::ZERO,
+                protocol_fee: Decimal::ZERO,
+            });
+
+            self.deposit(input_used);
+            let output_bucket = self.withdraw(output_resource_address, desired_output);
+
+            (output_bucket, input_bucket)
+        }
+
+        /// Returns the amount of the other resource that `swap` would currently give for
+        /// `input_amount` of `input_resource`.
+        pub fn get_amount_out(
+            &self,
+            input_resource: ResourceAddress,
+            input_amount: Decimal,
+        ) -> Decimal {
+            let (input_reserves, _, output_reserves) = self.reserves_for(input_resource);
+            Self::calculate_output_amount(input_amount, input_reserves, output_reserves)
+        }
+
+        /// Returns the amount of the other resource that `swap_exact_output` would currently take
+        /// to give `output_amount` of `output_resource`.
+        pub fn get_amount_in(
+            &self,
+            output_resource: ResourceAddress,
+            output_amount: Decimal,
+        ) -> Decimal {
+            let (output_reserves, _, input_reserves) = self.reserves_for(output_resource);
+            Self::calculate_input_amount(output_amount, input_reserves, output_reserves)
+        }
+
+        /// Returns the marginal price of `resource` expressed in the other resource of the pool.
+        pub fn spot_price(&self, resource: ResourceAddress) -> Decimal {
+            let (reserves, _, other_reserves) = self.reserves_for(resource);
+            other_reserves.checked_div(reserves).unwrap()
+        }
+
+        /// Returns how much worse than the spot price a swap of `input_amount` of `input_resource`
+        /// would execute, as a fraction between 0 and 1.
+        pub fn price_impact(
+            &self,
+            input_resource: ResourceAddress,
+            input_amount: Decimal,
+        ) -> Decimal {
+            assert!(
+                input_amount > Decimal::ZERO,
+                "Input amount must be positive"
+            );
+
+            let output_amount = self.get_amount_out(input_resource, input_amount);
+            let execution_price = output_amount.checked_div(input_amount).unwrap();
+
+            Decimal::ONE
+                .checked_sub(
+                    execution_price
+                        .checked_div(self.spot_price(input_resource))
+                        .unwrap(),
+                )
+                .unwrap()
+        }
+
+        /// Prices a swap of `input_amount` so that the product of the reserves is unchanged. Both
+        /// swaps and quotes go through this function so that a quote always matches what the swap
+        /// would do.
+        fn calculate_output_amount(
+            input_amount: Decimal,
+            input_reserves: Decimal,
+            output_reserves: Decimal,
+        ) -> Decimal {
+            input_amount
+                .checked_mul(output_reserves)
+                .unwrap()
+                .checked_div(input_reserves.checked_add(input_amount).unwrap())
+                .unwrap()
+        }
+
+        /// The inverse of `calculate_output_amount`: the input that must be paid to receive
+        /// `output_amount`. The result is rounded up by one atto so that rounding always favors
+        /// the pool.
+        fn calculate_input_amount(
+            output_amount: Decimal,
+            input_reserves: Decimal,
+            output_reserves: Decimal,
+        ) -> Decimal {
+            assert!(
+                output_amount < output_reserves,
+                "Not enough liquidity in the pool for the desired output"
+            );
+
+            output_amount
+                .checked_mul(input_reserves)
+                .unwrap()
+                .checked_div(output_reserves.checked_sub(output_amount).unwrap())
+                .unwrap()
+                .checked_add(dec!("0.000000000000000001"))
+                .unwrap()
+        }
+
+        /// Returns the reserves of the given resource along with the address and reserves of the
+        /// other resource of the pool.
+        fn reserves_for(
+            &self,
+            resource_address: ResourceAddress,
+        ) -> (Decimal, ResourceAddress, Decimal) {
+            let mut reserves = self.vault_reserves();
+
+            let resource_reserves = reserves
+                .remove(&resource_address)
+                .expect("Resource does not belong to the pool");
+            let (other_resource_address, other_reserves) = reserves.into_iter().next().unwrap();
+
+            (resource_reserves, other_resource_address, other_reserves)
+        }
+
+        fn assert_deadline_not_passed(deadline_epoch: Epoch) {
+            assert!(
+                Runtime::current_epoch() <= deadline_epoch,
+                "Deadline epoch {} has passed",
+                deadline_epoch.number()
+            );
+        }
+
         fn vault_reserves(&self) -> IndexMap<ResourceAddress, Decimal> {
             self.pool_component.get_vault_amounts()
         }
@@ -134,7 +324,12 @@ pub struct InstantiationEvent {
 }
 
 #[derive(ScryptoSbor, ScryptoEvent)]
-pub struct AddLiquidityEvent([(ResourceAddress, Decimal); 2]);
+pub struct AddLiquidityEvent {
+    /// The resources taken in by the pool, net of any change given back.
+    pub contributed_resources: [(ResourceAddress, Decimal); 2],
+    /// The amount of pool units minted for the contribution.
+    pub pool_units_amount: Decimal,
+}
 
 #[derive(ScryptoSbor, ScryptoEvent)]
 pub struct RemoveLiquidityEvent {
@@ -146,4 +341,11 @@ pub struct RemoveLiquidityEvent {
 pub struct SwapEvent {
     pub input: (ResourceAddress, Decimal),
     pub output: (ResourceAddress, Decimal),
+    /// The part of the fee, in the input resource, which is left in the pool for the liquidity
+    /// providers.
+    pub lp_fee: Decimal,
+    /// The part of the fee, in the input resource, which is diverted to the protocol treasury.
+    /// This pool charges no fee, so both fee portions are always zero; they are there so that the
+    /// event has the same shape as the one of fee-charging Radiswap pools.
+    pub protocol_fee: Decimal,
 }
@@ -206,6 +206,71 @@ impl TestEnvironment {
             &NetworkDefinition::simulator()
         )
     }
+
+    // ******** Test the swap exact input method. ********
+    fn test_swap_exact_input(&mut self, min_output: Decimal) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .call_method(
+                self.account.account_address,
+                "withdraw",
+                manifest_args!(self.resource_a, dec!(10))
+            )
+            .take_all_from_worktop(self.resource_a, "resource_in")
+            .call_method_with_name_lookup(
+                self.radiswap_component, 
+                "swap_exact_input", 
+                |lookup | (lookup.bucket("resource_in"), min_output, Epoch::of(100)),
+            )
+            .try_deposit_batch_or_abort(self.account.account_address, ManifestExpression::EntireWorktop, None);
+
+        self.execute_manifest_ignoring_fee(
+            manifest.object_names(),
+            manifest.build(),
+            "swap_exact_input",
+            &NetworkDefinition::simulator()
+        )
+    }
+
+    // ******** Test the swap exact output method. ********
+    fn test_swap_exact_output(&mut self, desired_output: Decimal) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .call_method(
+                self.account.account_address,
+                "withdraw",
+                manifest_args!(self.resource_a, dec!(100))
+            )
+            .take_all_from_worktop(self.resource_a, "resource_in")
+            .call_method_with_name_lookup(
+                self.radiswap_component, 
+                "swap_exact_output", 
+                |lookup | (lookup.bucket("resource_in"), desired_output, Epoch::of(100)),
+            )
+            .try_deposit_batch_or_abort(self.account.account_address, ManifestExpression::EntireWorktop, None);
+
+        self.execute_manifest_ignoring_fee(
+            manifest.object_names(),
+            manifest.build(),
+            "swap_exact_output",
+            &NetworkDefinition::simulator()
+        )
+    }
+
+    // ******** Test the get amount out method. ********
+    fn test_get_amount_out(&mut self, input_amount: Decimal) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .call_method(
+                self.radiswap_component,
+                "get_amount_out",
+                manifest_args!(self.resource_a, input_amount)
+            );
+
+        self.execute_manifest_ignoring_fee(
+            manifest.object_names(),
+            manifest.build(),
+            "get_amount_out",
+            &NetworkDefinition::simulator()
+        )
+    }
 }
 
 #[test]
@@ -235,4 +300,81 @@ fn swap() {
     let mut test_environment = TestEnvironment::instantiate_test();
     let receipt = test_environment.test_swap();
     receipt.expect_commit_success();
-}
\ No newline at end of file
+}
+
+#[test]
+fn swap_exact_input() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    let receipt = test_environment.test_swap_exact_input(dec!(9));
+    receipt.expect_commit_success();
+}
+
+#[test]
+fn swap_exact_input_below_minimum_output() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    let receipt = test_environment.test_swap_exact_input(dec!(10));
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn swap_exact_output() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    let receipt = test_environment.test_swap_exact_output(dec!(10));
+    receipt.expect_commit_success();
+}
+
+#[test]
+fn swap_exact_output_refunds_unused_input() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    let receipt = test_environment.test_swap_exact_output(dec!(10));
+    receipt.expect_commit_success();
+
+    // About 11.11 of the 100 A withdrawn are used, the rest is back in the account.
+    let balance_a = test_environment.test_runner
+        .account_balance(test_environment.account.account_address, test_environment.resource_a)
+        .unwrap();
+    assert!(balance_a > dec!("888.88") && balance_a < dec!("888.89"));
+    assert_eq!(
+        test_environment.test_runner
+            .account_balance(test_environment.account.account_address, test_environment.resource_b),
+        Some(dec!(910))
+    );
+}
+
+#[test]
+fn swap_exact_input_after_deadline() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    test_environment.test_runner.set_current_epoch(Epoch::of(101));
+    let receipt = test_environment.test_swap_exact_input(dec!(9));
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn swap_exact_output_after_deadline() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    test_environment.test_runner.set_current_epoch(Epoch::of(101));
+    let receipt = test_environment.test_swap_exact_output(dec!(10));
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn get_amount_out_matches_swap() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    test_environment.test_add_liquidity();
+    let receipt = test_environment.test_get_amount_out(dec!(10));
+    let quoted_output: Decimal = receipt.expect_commit_success().output(0);
+
+    let receipt = test_environment.test_swap_exact_input(dec!(9));
+    receipt.expect_commit_success();
+    assert_eq!(
+        test_environment.test_runner
+            .account_balance(test_environment.account.account_address, test_environment.resource_b),
+        Some(dec!(900).checked_add(quoted_output).unwrap())
+    );
+}
@@ -2,6 +2,18 @@ use scrypto::prelude::*;
 
 #[blueprint]
 mod radiswap {
+    // Everyone may trade and provide liquidity. Moving resources in and out of the pool without
+    // going through the pricing of `swap` is only done internally, so `deposit` and `withdraw` are
+    // not exposed as methods at all.
+    enable_method_auth! {
+        methods {
+            add_liquidity => PUBLIC;
+            remove_liquidity => PUBLIC;
+            swap => PUBLIC;
+            vault_reserves => PUBLIC;
+        }
+    }
+
     struct Radiswap {
         liquidity_pool_component: Global<TwoResourcePool>,
     }
@@ -33,7 +45,7 @@ mod radiswap {
                 liquidity_pool_component,
             }
             .instantiate()
-            .prepare_to_globalize(OwnerRole::None)
+            .prepare_to_globalize(owner_role)
             .with_address(address_reservation)
             .globalize()
         }
@@ -85,11 +97,11 @@ mod radiswap {
             self.liquidity_pool_component.get_vault_amounts()
         }
 
-        pub fn deposit(&mut self, bucket: Bucket) {
+        fn deposit(&mut self, bucket: Bucket) {
             self.liquidity_pool_component.protected_deposit(bucket)
         }
 
-        pub fn withdraw(&mut self, token_address: ResourceAddress, amount: Decimal) -> Bucket {
+        fn withdraw(&mut self, token_address: ResourceAddress, amount: Decimal) -> Bucket {
             self.liquidity_pool_component.protected_withdraw(
                 token_address,
                 amount,
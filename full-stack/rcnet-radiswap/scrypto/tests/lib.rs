@@ -0,0 +1,197 @@
+use radix_engine::errors::{RuntimeError, SystemModuleError};
+use scrypto::prelude::*;
+use scrypto_unit::*;
+use transaction::builder::ManifestBuilder;
+
+pub struct Account {
+    public_key: Secp256k1PublicKey,
+    account_address: ComponentAddress,
+}
+
+pub struct TestEnvironment {
+    test_runner: DefaultTestRunner,
+    owner: Account,
+    outsider: Account,
+    token_a: ResourceAddress,
+    token_b: ResourceAddress,
+    radiswap_component: ComponentAddress,
+}
+
+impl TestEnvironment {
+    // ******** Setup the environment with a funded pool owned by the owner account ********
+    pub fn instantiate_test() -> Self {
+        let mut test_runner = TestRunnerBuilder::new().build();
+
+        // Create the owner and outsider accounts
+        let (public_key, _private_key, account_address) = test_runner.new_allocated_account();
+        let owner = Account {
+            public_key,
+            account_address,
+        };
+        let (public_key, _private_key, account_address) = test_runner.new_allocated_account();
+        let outsider = Account {
+            public_key,
+            account_address,
+        };
+
+        // Publish package
+        let package_address = test_runner.compile_and_publish(this_package!());
+
+        // Create two fungible resources for the pool, the outsider gets some of token A to swap
+        let token_a = test_runner.create_fungible_resource(dec!(1000), 18u8, owner.account_address);
+        let token_b = test_runner.create_fungible_resource(dec!(1000), 18u8, owner.account_address);
+        let manifest = ManifestBuilder::new()
+            .withdraw_from_account(owner.account_address, token_a, dec!(100))
+            .deposit_batch(outsider.account_address)
+            .build();
+        test_runner
+            .execute_manifest_ignoring_fee(
+                manifest,
+                vec![NonFungibleGlobalId::from_public_key(&owner.public_key)],
+            )
+            .expect_commit_success();
+
+        // Instantiate Radiswap, owned by the owner account's signature
+        let manifest = ManifestBuilder::new()
+            .call_function(
+                package_address,
+                "Radiswap",
+                "instantiate_pool",
+                manifest_args!(
+                    OwnerRole::Fixed(rule!(require(NonFungibleGlobalId::from_public_key(
+                        &owner.public_key
+                    )))),
+                    token_a,
+                    token_b
+                ),
+            )
+            .build();
+        let receipt = test_runner.execute_manifest_ignoring_fee(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&owner.public_key)],
+        );
+        let radiswap_component = receipt.expect_commit_success().new_component_addresses()[0];
+
+        // Provide the initial liquidity
+        let manifest = ManifestBuilder::new()
+            .withdraw_from_account(owner.account_address, token_a, dec!(100))
+            .withdraw_from_account(owner.account_address, token_b, dec!(100))
+            .take_all_from_worktop(token_a, "token_a")
+            .take_all_from_worktop(token_b, "token_b")
+            .call_method_with_name_lookup(radiswap_component, "add_liquidity", |lookup| {
+                (lookup.bucket("token_a"), lookup.bucket("token_b"))
+            })
+            .deposit_batch(owner.account_address)
+            .build();
+        test_runner
+            .execute_manifest_ignoring_fee(
+                manifest,
+                vec![NonFungibleGlobalId::from_public_key(&owner.public_key)],
+            )
+            .expect_commit_success();
+
+        Self {
+            test_runner,
+            owner,
+            outsider,
+            token_a,
+            token_b,
+            radiswap_component,
+        }
+    }
+}
+
+#[test]
+fn outsider_can_swap() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    let outsider_address = test_environment.outsider.account_address;
+
+    let manifest = ManifestBuilder::new()
+        .withdraw_from_account(outsider_address, test_environment.token_a, dec!(10))
+        .take_all_from_worktop(test_environment.token_a, "token_a")
+        .call_method_with_name_lookup(test_environment.radiswap_component, "swap", |lookup| {
+            (lookup.bucket("token_a"),)
+        })
+        .deposit_batch(outsider_address)
+        .build();
+    let receipt = test_environment.test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(
+            &test_environment.outsider.public_key,
+        )],
+    );
+    receipt.expect_commit_success();
+}
+
+// `withdraw` is a private helper of the blueprint, so calling it fails because the method does not
+// exist on the component rather than because of its auth.
+#[test]
+fn withdraw_is_not_exposed_on_the_component() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    let outsider_address = test_environment.outsider.account_address;
+
+    let manifest = ManifestBuilder::new()
+        .call_method(
+            test_environment.radiswap_component,
+            "withdraw",
+            manifest_args!(test_environment.token_b, dec!(100)),
+        )
+        .deposit_batch(outsider_address)
+        .build();
+    let receipt = test_environment.test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(
+            &test_environment.outsider.public_key,
+        )],
+    );
+    receipt.expect_commit_failure();
+    assert_eq!(
+        test_environment
+            .test_runner
+            .account_balance(outsider_address, test_environment.token_b),
+        None
+    );
+}
+
+#[test]
+fn owner_role_is_wired_to_the_instantiation_argument() {
+    let mut test_environment = TestEnvironment::instantiate_test();
+    let radiswap_component = test_environment.radiswap_component;
+
+    // The outsider may not update the component's metadata
+    let manifest = ManifestBuilder::new()
+        .set_metadata(
+            radiswap_component,
+            "name",
+            MetadataValue::String("Radiswap".to_owned()),
+        )
+        .build();
+    let receipt = test_environment.test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(
+            &test_environment.outsider.public_key,
+        )],
+    );
+    receipt.expect_specific_failure(|error| {
+        matches!(
+            error,
+            RuntimeError::SystemModuleError(SystemModuleError::AuthError(..))
+        )
+    });
+
+    // The owner passed to `instantiate_pool` may
+    let manifest = ManifestBuilder::new()
+        .set_metadata(
+            radiswap_component,
+            "name",
+            MetadataValue::String("Radiswap".to_owned()),
+        )
+        .build();
+    let receipt = test_environment.test_runner.execute_manifest_ignoring_fee(
+        manifest,
+        vec![NonFungibleGlobalId::from_public_key(
+            &test_environment.owner.public_key,
+        )],
+    );
+    receipt.expect_commit_success();
+}